@@ -3,24 +3,55 @@ use arena::gui::input::{
     Backspace, Copy, Cut, Delete, End, Home, InputController, InputField, Left, Paste, Right,
     SelectAll, SelectLeft, SelectRight, ShowCharacterPalette,
 };
-use arena::{AnalysisLine, Engine, gui};
+use arena::setup::Setup;
+use arena::tree::{GameTree, ROOT};
+use arena::{
+    AnalysisLine, Engine, Game, GameCommand, GameEvent, Outcome, Termination, gui, notation, pgn,
+    position_outcome,
+};
 use gpui::{
-    App, Application, AsyncApp, Bounds, Context, Corner, ElementId, Focusable,
-    KeyBinding, MouseButton, SharedString,  TitlebarOptions, Window,
-    WindowBounds, WindowOptions, anchored, deferred, div, prelude::*, px, rgb, size,
+    Animation, AnimationExt, App, Application, AsyncApp, Bounds, Context, Corner, Div, ElementId,
+    Focusable, Hsla, KeyBinding, MouseButton, Pixels, ScrollHandle, SharedString, Stateful, TitlebarOptions,
+    Window, WindowBounds, WindowOptions, anchored, deferred, div, ease_in_out, img, prelude::*, px,
+    rgb, size,
 };
 use queenfish::board::Move;
 use queenfish::board::bishop_magic::init_bishop_magics;
 use queenfish::board::rook_magic::init_rook_magics;
 use queenfish::board::{Board as QueenFishBoard, UnMakeMove};
 use rfd::FileDialog;
-use std::{collections::HashSet};
+use std::{
+    collections::HashSet,
+    path::Path,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+use arena::gui::arrows::{BoardArrow, arrows_overlay};
 use arena::gui::fen_window::FenWindow;
+use arena::gui::play::{self, NewGame, NewMatch, TIME_CONTROLS, VsEngine, WatchedGame, WatchedLine};
+use arena::gui::settings::GuiSettings;
 use arena::gui::state::SharedState;
-use arena::gui::components::{board_square, logo_button, menu_button, seperator};
+use arena::gui::components::{
+    DraggedPiece, board_square, button, check_box, logo_button, menu_button, piece_image,
+    seperator,
+};
 use arena::gui::state::EnginesServices;
 use arena::gui::engine_options::EngineOptionsWindow;
+use arena::gui::keymap::{
+    self, BOARD_CONTEXT, FirstMove, FlipBoard, LastMove, LoadFen, NextMove, PreviousMove,
+    ResetBoard, ToggleAnalysis,
+};
 
+/// A piece sliding from `from` to `to`, drawn over the board until `started` is older than
+/// the animation duration
+struct MoveAnimation {
+    /// new for every animation so gpui restarts it
+    id: usize,
+    from: usize,
+    to: usize,
+    piece: usize,
+    started: Instant,
+}
 
 struct Board {
     board: QueenFishBoard,
@@ -28,11 +59,55 @@ struct Board {
     available_moves: Vec<(usize, usize)>,
     is_analyzing: bool,
     selected_square: Option<usize>,
+    /// every move played or loaded, side variations included
+    tree: GameTree,
+    /// tree node of the position shown
+    current_node: usize,
+    /// unmakes for the moves leading to the position shown
     unmake_move_history: Vec<UnMakeMove>,
+    /// the line through `current_node`, continued along its main line
     make_move_history: Vec<Move>,
     current_move_index: usize,
     is_engines_menu_open: bool,
     is_board_flipped: bool,
+    error_message: Option<String>,
+    /// position the history starts from, None for the standard start
+    start_fen: Option<String>,
+    /// (from, to) of a pawn move waiting for the promotion piece to be picked
+    pending_promotion: Option<(usize, usize)>,
+    /// position being edited, Some while in setup mode
+    setup: Option<Setup>,
+    /// piece placed by clicking in setup mode, None erases
+    setup_brush: Option<usize>,
+    /// arrows and circles drawn with the right mouse button, cleared when the position changes
+    user_arrows: Vec<(usize, usize)>,
+    user_circles: Vec<usize>,
+    /// square a right-button drag started on
+    arrow_start: Option<usize>,
+    /// also draw each engine's second MultiPV move
+    show_second_arrow: bool,
+    /// loaded engine whose best line drives the eval bar, picked by clicking its name
+    eval_engine: usize,
+    animation: Option<MoveAnimation>,
+    animation_count: usize,
+    /// choices for a game against an engine, Some while the dialog is open
+    new_game: Option<NewGame>,
+    /// game against an engine, kept after it ends until another one starts or the board is reset
+    vs_engine: Option<VsEngine>,
+    /// process playing `vs_engine`, apart from the loaded engines so analysis is left alone
+    game_engine: Option<Engine>,
+    /// every game of the PGN file loaded last, when it held more than one
+    pgn_games: Vec<pgn::PgnGame>,
+    pgn_game_index: usize,
+    /// engines picked for a game to watch, Some while the dialog is open
+    new_match: Option<NewMatch>,
+    /// engine game running on its own thread, kept after it ends like `vs_engine`
+    watching: Option<WatchedGame>,
+    game_count: usize,
+    settings: GuiSettings,
+    move_list_scroll: ScrollHandle,
+    /// tree node the move list was last scrolled to
+    move_list_scrolled_to: usize,
 }
 
 impl Focusable for Board {
@@ -43,6 +118,12 @@ impl Focusable for Board {
 
 impl Board {
     pub fn select_square(&mut self, square: usize) {
+        self.pending_promotion = None;
+        if self.vs_engine.as_ref().is_some_and(|game| game.is_engine_turn())
+            || self.watching.as_ref().is_some_and(|game| !game.is_over())
+        {
+            return;
+        }
         match self.board.game_result() {
             queenfish::board::GameResult::InProgress => {}
             _ => {
@@ -73,11 +154,17 @@ impl Board {
                 .iter()
                 .find(|mv| mv.1 == square)
                 .unwrap();
-            let mv = moves
+            let candidates = moves
                 .iter()
-                .find(|mv| (mv.from(), mv.to()) == *selected_mv)
-                .unwrap();
-            self.play_move(mv.to_uci());
+                .filter(|mv| (mv.from(), mv.to()) == *selected_mv)
+                .collect::<Vec<_>>();
+            // only promotions share a from and to square, the piece is picked on the board
+            if candidates.len() > 1 {
+                self.pending_promotion = Some(*selected_mv);
+                self.available_moves = Vec::new();
+                return;
+            }
+            self.play_move(candidates[0].to_uci());
             self.available_moves = Vec::new();
             return;
         } else {
@@ -108,11 +195,36 @@ impl Board {
             // engine_handle: Some(engine_handle),
             is_analyzing: false,
             selected_square: None,
+            tree: GameTree::new(),
+            current_node: ROOT,
             unmake_move_history: Vec::new(),
             make_move_history: Vec::new(),
             current_move_index: 0,
             is_engines_menu_open: false,
             is_board_flipped: false,
+            error_message: None,
+            start_fen: None,
+            pending_promotion: None,
+            setup: None,
+            setup_brush: None,
+            user_arrows: Vec::new(),
+            user_circles: Vec::new(),
+            arrow_start: None,
+            show_second_arrow: false,
+            eval_engine: 0,
+            animation: None,
+            animation_count: 0,
+            new_game: None,
+            vs_engine: None,
+            game_engine: None,
+            pgn_games: Vec::new(),
+            pgn_game_index: 0,
+            new_match: None,
+            watching: None,
+            game_count: 0,
+            settings: GuiSettings::load(),
+            move_list_scroll: ScrollHandle::new(),
+            move_list_scrolled_to: ROOT,
         };
 
         return element;
@@ -122,48 +234,781 @@ impl Board {
         self.board = QueenFishBoard::new();
         self.available_moves = Vec::new();
         self.current_move_index = 0;
+        self.tree = GameTree::new();
+        self.current_node = ROOT;
         self.make_move_history = Vec::new();
         self.unmake_move_history = Vec::new();
         self.is_analyzing = false;
+        self.start_fen = None;
+        self.animation = None;
+        self.vs_engine = None;
+        self.game_engine = None;
+        // dropping the command channel aborts a watched game
+        self.watching = None;
+        self.pgn_games = Vec::new();
+        self.clear_drawings();
     } //
 
     pub fn load_from_fen(&mut self, fen: String) {
+        self.reset_board();
         self.board.load_from_fen(fen.as_str());
+        self.start_fen = Some(fen);
     } //
 
-    pub fn play_move(&mut self, mv: String) {
-        if self.current_move_index != self.make_move_history.len() {
-            self.make_move_history.truncate(self.current_move_index);
-            self.unmake_move_history.truncate(self.current_move_index);
+    /// Replaces the history with `game` and its variations, showing its start position
+    pub fn load_pgn_game(&mut self, game: &pgn::PgnGame) {
+        match game.start_fen() {
+            Some(fen) => self.load_from_fen(fen.to_string()),
+            None => self.reset_board(),
+        }
+        self.tree = game.to_tree();
+        self.make_move_history = self.tree.line_moves(ROOT);
+    } //
+
+    /// Loads game `index` of the last PGN file, keeping the others to pick from
+    pub fn load_pgn_file_game(&mut self, index: usize) {
+        let games = std::mem::take(&mut self.pgn_games);
+        if let Some(game) = games.get(index) {
+            self.load_pgn_game(game);
+            self.pgn_game_index = index;
+        }
+        self.pgn_games = games;
+    } //
+
+    /// The whole game tree as PGN
+    pub fn to_pgn(&self) -> String {
+        pgn::tree_to_pgn(self.start_fen.as_deref(), &self.tree)
+    } //
+
+    fn start_board(&self) -> QueenFishBoard {
+        let mut board = QueenFishBoard::new();
+        if let Some(fen) = &self.start_fen {
+            board.load_from_fen(fen);
         }
+        board
+    } //
 
+    /// Shows the position after `node`, which may be in any variation
+    pub fn go_to_node(&mut self, node: usize) {
+        if self.is_playing() {
+            return;
+        }
+        let previous = self.current_node;
         self.is_analyzing = false;
+        self.clear_drawings();
+        self.board = self.start_board();
+        self.unmake_move_history = self
+            .tree
+            .path(node)
+            .into_iter()
+            .filter_map(|node| self.tree.mv(node))
+            .map(|mv| self.board.make_move(mv))
+            .collect();
+        self.current_move_index = self.unmake_move_history.len();
+        self.current_node = node;
+        self.make_move_history = self.tree.line_moves(node);
+
+        // a single step either way slides like the arrow buttons do
+        self.animation = None;
+        if self.tree.parent(node) == Some(previous) {
+            self.animate_last_move();
+        } else if let Some(mv) = self.tree.mv(previous).filter(|_| self.tree.parent(previous) == Some(node)) {
+            self.animate_move(mv.to(), mv.from());
+        }
+    } //
+
+    /// Makes the line of the current move the main line
+    pub fn promote_variation(&mut self) {
+        if self.is_playing() {
+            return;
+        }
+        self.tree.promote(self.current_node);
+        self.make_move_history = self.tree.line_moves(self.current_node);
+    } //
+
+    /// Deletes the current move and everything after it, showing the position before it
+    pub fn delete_variation(&mut self) {
+        if self.is_playing() {
+            return;
+        }
+        let node = self.current_node;
+        if let Some(parent) = self.tree.parent(node) {
+            self.undo_move();
+            self.tree.delete(node);
+            self.current_node = parent;
+            self.make_move_history = self.tree.line_moves(parent);
+        }
+    } //
+
+    /// Finishes a drag. Dropping on a legal target plays the move like a second click would,
+    /// anything else leaves the piece where it was.
+    pub fn drop_piece(&mut self, from: usize, to: usize) {
+        if self.selected_square == Some(from) && self.available_moves.contains(&(from, to)) {
+            self.select_square(to);
+        }
+    } //
+
+    /// Plays the pending promotion with `piece`, one of 'q', 'r', 'b' or 'n'
+    pub fn promote(&mut self, piece: char) {
+        let Some((from, to)) = self.pending_promotion.take() else {
+            return;
+        };
+        let mv = self
+            .board
+            .generate_moves()
+            .iter()
+            .find(|mv| (mv.from(), mv.to()) == (from, to) && mv.to_uci().ends_with(piece))
+            .copied();
+        if let Some(mv) = mv {
+            self.play_move(mv.to_uci());
+        }
+    } //
+
+    /// Plays a move from the position shown. A move that differs from the one played here
+    /// before starts a side variation instead of replacing it.
+    pub fn play_move(&mut self, mv: String) {
+        self.is_analyzing = false;
+        self.clear_drawings();
         let mv = Move::from_uci(mv.as_str(), &(self.board));
+        self.current_node = self.tree.add_move(self.current_node, mv);
         let unmakemove = self.board.make_move(mv);
-        self.make_move_history.push(mv);
+        self.unmake_move_history.truncate(self.current_move_index);
         self.unmake_move_history.push(unmakemove);
         self.current_move_index += 1;
+        self.make_move_history = self.tree.line_moves(self.current_node);
+        self.animation = None;
+        if let Some(game) = self.vs_engine.as_mut().filter(|game| !game.is_over()) {
+            game.press_clock();
+        }
     } //
 
     pub fn move_forward(&mut self) {
-        if self.current_move_index as i32 > (self.make_move_history.len() as i32) - 1 {
+        if self.is_playing() || self.current_move_index as i32 > (self.make_move_history.len() as i32) - 1 {
             return;
         }
         self.is_analyzing = false;
+        self.clear_drawings();
         let mv = self.make_move_history[self.current_move_index];
-        self.board.make_move(mv);
+        let unmakemove = self.board.make_move(mv);
+        self.unmake_move_history.truncate(self.current_move_index);
+        self.unmake_move_history.push(unmakemove);
         self.current_move_index += 1;
+        // the line continues along first children, see `GameTree::line`
+        if let Some(&child) = self.tree.children(self.current_node).first() {
+            self.current_node = child;
+        }
+        self.animate_move(mv.from(), mv.to());
     } //
 
     pub fn undo_move(&mut self) {
-        if self.current_move_index <= 0 {
+        if self.is_playing() || self.current_move_index <= 0 {
             return;
         }
         let current_move_index = self.current_move_index - 1;
         self.is_analyzing = false;
+        self.clear_drawings();
         let unmake = self.unmake_move_history[current_move_index];
         self.board.unmake_move(unmake);
         self.current_move_index -= 1;
+        if let Some(parent) = self.tree.parent(self.current_node) {
+            self.current_node = parent;
+        }
+        let mv = self.make_move_history[current_move_index];
+        self.animate_move(mv.to(), mv.from());
+    } //
+
+    /// Steps through the history until `index` moves are played
+    pub fn go_to_move(&mut self, index: usize) {
+        let index = index.min(self.make_move_history.len());
+        let steps = self.current_move_index.abs_diff(index);
+        while self.current_move_index < index {
+            self.move_forward();
+        }
+        while self.current_move_index > index {
+            self.undo_move();
+        }
+        // only a single step is animated, a jump shows the position at once
+        if steps > 1 {
+            self.animation = None;
+        }
+    } //
+
+    /// Slides the piece now standing on `to` over from `from`
+    fn animate_move(&mut self, from: usize, to: usize) {
+        self.animation_count += 1;
+        let id = self.animation_count;
+        self.animation = self.board.piece_at[to]
+            .filter(|_| self.settings.animation_ms > 0)
+            .map(|piece| MoveAnimation {
+                id,
+                from,
+                to,
+                piece: piece as usize,
+                started: Instant::now(),
+            });
+    } //
+
+    /// Animates the move leading to the position shown, e.g. after an engine played it
+    pub fn animate_last_move(&mut self) {
+        if let Some(mv) = self.tree.mv(self.current_node) {
+            self.animate_move(mv.from(), mv.to());
+        }
+    } //
+
+    /// The tree as rows for the move list: the main line in numbered pairs, each side
+    /// variation on its own row after the move it branches from
+    fn move_list_rows(&self) -> Vec<MoveListRow> {
+        let mut board = self.start_board();
+        let first_move_number = self
+            .start_fen
+            .as_ref()
+            .and_then(|fen| fen.split_whitespace().nth(5))
+            .and_then(|number| number.parse().ok())
+            .unwrap_or(1);
+        let mut rows = Vec::new();
+        let mut node = ROOT;
+        let mut move_number = first_move_number;
+        while let Some((&main, alternatives)) = self.tree.children(node).split_first() {
+            let Some(mv) = self.tree.mv(main) else {
+                break;
+            };
+            let is_white = matches!(board.turn, queenfish::board::Turn::WHITE);
+            let san = notation::move_to_san(&mut board, mv);
+            match rows.last_mut() {
+                Some(MoveListRow::Pair { black, .. }) if !is_white && black.is_none() => {
+                    *black = Some((main, san));
+                }
+                _ if is_white => rows.push(MoveListRow::Pair {
+                    number: move_number,
+                    white: Some((main, san)),
+                    black: None,
+                }),
+                _ => rows.push(MoveListRow::Pair {
+                    number: move_number,
+                    white: None,
+                    black: Some((main, san)),
+                }),
+            }
+            for &alternative in alternatives {
+                self.push_variation_rows(&mut board, alternative, move_number, 1, &mut rows);
+            }
+            board.make_move(mv);
+            if !is_white {
+                move_number += 1;
+            }
+            node = main;
+        }
+        rows
+    } //
+
+    fn push_variation_rows(
+        &self,
+        board: &mut QueenFishBoard,
+        first: usize,
+        move_number: usize,
+        depth: usize,
+        rows: &mut Vec<MoveListRow>,
+    ) {
+        let mut moves = Vec::new();
+        let mut nested = Vec::new();
+        let mut unmakes = Vec::new();
+        let mut node = first;
+        let mut move_number = move_number;
+        loop {
+            let Some(mv) = self.tree.mv(node) else {
+                break;
+            };
+            let is_white = matches!(board.turn, queenfish::board::Turn::WHITE);
+            let san = notation::move_to_san(board, mv);
+            let label = match (is_white, moves.is_empty()) {
+                (true, _) => format!("{}. {}", move_number, san),
+                (false, true) => format!("{}... {}", move_number, san),
+                (false, false) => san,
+            };
+            moves.push((node, label));
+            unmakes.push(board.make_move(mv));
+            if !is_white {
+                move_number += 1;
+            }
+            let Some((&main, alternatives)) = self.tree.children(node).split_first() else {
+                break;
+            };
+            for &alternative in alternatives {
+                self.push_variation_rows(board, alternative, move_number, depth + 1, &mut nested);
+            }
+            node = main;
+        }
+        for unmake in unmakes.into_iter().rev() {
+            board.unmake_move(unmake);
+        }
+        rows.push(MoveListRow::Variation { depth, moves });
+        rows.extend(nested);
+    } //
+
+    pub fn enter_setup(&mut self) {
+        if self.is_playing() {
+            return;
+        }
+        let fen = self.board.to_fen();
+        self.setup = Some(Setup::from_fen(&fen).unwrap_or_else(Setup::start));
+        self.selected_square = None;
+        self.available_moves = Vec::new();
+        self.pending_promotion = None;
+    } //
+
+    /// Loads the edited position if it is valid, otherwise stays in setup mode
+    pub fn finish_setup(&mut self) {
+        let Some(setup) = &self.setup else {
+            return;
+        };
+        match setup.validate() {
+            Ok(()) => {
+                let fen = setup.to_fen();
+                self.setup = None;
+                self.load_from_fen(fen);
+            }
+            Err(err) => self.error_message = Some(err.to_string()),
+        }
+    } //
+
+    /// Places the brush piece on `square`, or clears it when it already holds that piece
+    pub fn setup_click(&mut self, square: usize) {
+        let brush = self.setup_brush;
+        if let Some(setup) = &mut self.setup {
+            setup.pieces[square] = if setup.pieces[square] == brush { None } else { brush };
+            setup.en_passant = None;
+        }
+    } //
+
+    /// Drops a piece dragged from the palette or another square, `None` takes it off the board
+    pub fn setup_drop(&mut self, dragged: &DraggedPiece, square: Option<usize>) {
+        if let Some(setup) = &mut self.setup {
+            if let Some(from) = dragged.from {
+                setup.pieces[from] = None;
+            }
+            if let Some(square) = square {
+                setup.pieces[square] = Some(dragged.piece);
+            }
+            setup.en_passant = None;
+        }
+    } //
+
+    /// Starting a game replaces the moves and variations on the board
+    fn has_moves(&self) -> bool {
+        !self.tree.children(ROOT).is_empty()
+    } //
+
+    /// A game against an engine is running, the history cannot be browsed until it ends
+    fn is_playing(&self) -> bool {
+        self.vs_engine.as_ref().is_some_and(|game| !game.is_over())
+            || self.watching.as_ref().is_some_and(|game| !game.is_over())
+    } //
+
+    /// Starts a game against an engine from the start position of the current history. The
+    /// engine gets a process of its own, the game begins once its handshake is done.
+    pub fn start_vs_engine(&mut self, new_game: NewGame, cx: &mut Context<Self>) {
+        let Some(mut engine) = cx
+            .global::<SharedState>()
+            .engines
+            .engines
+            .get(new_game.engine_index)
+            .cloned()
+        else {
+            self.error_message = Some("Load an engine first".to_string());
+            return;
+        };
+        let engine_name = engine.name.clone();
+        let task = cx.spawn(async move |this, cx: &mut AsyncApp| {
+            let started = cx
+                .background_spawn(async move { engine.start().map(|_| engine) })
+                .await;
+            let _ = this.update(cx, |board, cx| {
+                match started {
+                    Ok(engine) => board.begin_vs_engine(new_game, engine, cx),
+                    Err(err) => {
+                        board.error_message =
+                            Some(format!("Failed to start {}: {}", engine_name, err))
+                    }
+                }
+                cx.notify();
+            });
+        });
+        task.detach();
+    } //
+
+    /// Starts the registry's engines one after another off the UI thread, each failure is
+    /// added to the error message with the entry it came from
+    pub fn load_registry_engines(&mut self, cx: &mut Context<Self>) {
+        let entries = cx.global::<SharedState>().engines.registry.engines.clone();
+        let task = cx.spawn(async move |this, cx: &mut AsyncApp| {
+            for entry in entries {
+                let loading = entry.clone();
+                let loaded = cx.background_spawn(async move { loading.load_engine() }).await;
+                let _ = this.update(cx, |board, cx| {
+                    match loaded {
+                        Ok(engine) => cx.global_mut::<SharedState>().engines.engines.push(engine),
+                        Err(err) => {
+                            let message =
+                                format!("Failed to start {} ({}): {}", entry.name, entry.path, err);
+                            board.error_message = Some(match board.error_message.take() {
+                                Some(earlier) => format!("{}\n{}", earlier, message),
+                                None => message,
+                            });
+                        }
+                    }
+                    cx.notify();
+                });
+            }
+        });
+        task.detach();
+    } //
+
+    fn begin_vs_engine(&mut self, new_game: NewGame, mut engine: Engine, cx: &mut Context<Self>) {
+        engine.send_command("ucinewgame\n");
+        let engine_name = engine.name.clone();
+        let engines = &mut cx.global_mut::<SharedState>().engines;
+        if engines.is_analyzing {
+            engines.toggle_analyze(&self.board);
+        }
+
+        self.new_game = None;
+        self.watching = None;
+        // the process of an earlier game is killed when it is dropped
+        self.game_engine = Some(engine);
+        self.tree = GameTree::new();
+        self.go_to_node(ROOT);
+        self.is_board_flipped = !new_game.user_is_white;
+        self.game_count += 1;
+        let id = self.game_count;
+        self.vs_engine = Some(VsEngine::new(
+            id,
+            engine_name,
+            new_game.user_is_white,
+            TIME_CONTROLS[new_game.time_control_index].1,
+            matches!(self.board.turn, queenfish::board::Turn::WHITE),
+        ));
+
+        // the engine's replies and the clocks are checked a few times a second
+        let task = cx.spawn(async move |this, cx: &mut AsyncApp| {
+            loop {
+                cx.background_executor().timer(Duration::from_millis(100)).await;
+                let is_running = this
+                    .update(cx, |board, cx| {
+                        let is_running = board.tick_vs_engine(id);
+                        cx.notify();
+                        is_running
+                    })
+                    .unwrap_or(false);
+                if !is_running {
+                    break;
+                }
+            }
+        });
+        task.detach();
+    } //
+
+    /// Ends the game on the board, flags the side out of time and lets the engine move.
+    /// Returns false once game `id` is over or replaced.
+    fn tick_vs_engine(&mut self, id: usize) -> bool {
+        if !self
+            .vs_engine
+            .as_ref()
+            .is_some_and(|game| game.id == id && !game.is_over())
+        {
+            return false;
+        }
+        let turn = self.board.turn;
+        let mut ending = position_outcome(&mut self.board);
+        if ending.is_none() && self.vs_engine.as_ref().is_some_and(|game| game.is_flagged()) {
+            ending = Some((Outcome::loss_for(turn), Termination::TimeForfeit));
+        }
+        if ending.is_none() && self.vs_engine.as_ref().is_some_and(|game| game.is_engine_turn()) {
+            ending = self.engine_turn();
+        }
+        match ending {
+            Some((outcome, termination)) => {
+                self.finish_vs_engine(outcome, termination);
+                false
+            }
+            None => true,
+        }
+    } //
+
+    /// Sends the engine the position when its clock starts and plays its reply once it arrives
+    fn engine_turn(&mut self) -> Option<(Outcome, Termination)> {
+        let turn = self.board.turn;
+        let position = self.position_command();
+        let game = self.vs_engine.as_mut()?;
+        let Some(engine) = self.game_engine.as_mut() else {
+            return Some((Outcome::loss_for(turn), Termination::EngineCrash));
+        };
+        engine.poll_engine();
+
+        let best_moves = engine
+            .analysis
+            .iter()
+            .filter_map(|line| match line {
+                AnalysisLine::Move(best_move) => Some(best_move.clone()),
+                AnalysisLine::Info(_) => None,
+            })
+            .collect::<Vec<_>>();
+        engine.analysis.retain(|line| !matches!(line, AnalysisLine::Move(_)));
+        let reply = best_moves.into_iter().last().filter(|_| game.is_engine_thinking);
+
+        if !game.is_engine_thinking {
+            engine.analysis.clear();
+            engine.lines.clear();
+            engine.send_command(position.as_str());
+            engine.send_command(game.go_command().as_str());
+            game.is_engine_thinking = true;
+            game.search_started = Instant::now();
+            return None;
+        }
+        // a clock flags an engine that went quiet, other time controls have a deadline
+        let Some(best_move) = reply else {
+            if engine.has_exited() {
+                return Some((Outcome::loss_for(turn), Termination::EngineCrash));
+            }
+            if game.is_search_overdue(&engine.timeouts) {
+                return Some((Outcome::loss_for(turn), Termination::TimeForfeit));
+            }
+            return None;
+        };
+        game.is_engine_thinking = false;
+        game.engine_score = engine.lines.first().and_then(|line| line.score);
+
+        match notation::find_uci_move(&mut self.board, &best_move) {
+            Some(mv) => {
+                self.play_move(mv.to_uci());
+                self.animate_last_move();
+                None
+            }
+            None => Some((Outcome::loss_for(turn), Termination::IllegalMove)),
+        }
+    } //
+
+    /// Records the result and appends the game to the saved games
+    fn finish_vs_engine(&mut self, outcome: Outcome, termination: Termination) {
+        let Some(game) = self.vs_engine.as_mut().filter(|game| !game.is_over()) else {
+            return;
+        };
+        // killing the process also ends a search still running
+        self.game_engine = None;
+        game.result = Some((outcome, termination));
+        let pgn = pgn::played_game_to_pgn(
+            game.players(),
+            self.start_fen.as_deref(),
+            &self.tree,
+            game.time_control,
+            outcome,
+            termination,
+        );
+        if let Err(err) = play::save_game(&pgn) {
+            self.error_message = Some(format!("Failed to save the game: {}", err));
+        }
+    } //
+
+    pub fn resign(&mut self) {
+        let Some(game) = self.vs_engine.as_ref().filter(|game| !game.is_over()) else {
+            return;
+        };
+        let outcome = if game.user_is_white {
+            Outcome::BlackWin
+        } else {
+            Outcome::WhiteWin
+        };
+        self.finish_vs_engine(outcome, Termination::Resignation);
+    } //
+
+    pub fn offer_draw(&mut self) {
+        let Some(game) = self.vs_engine.as_mut().filter(|game| !game.is_over()) else {
+            return;
+        };
+        if game.engine_accepts_draw() {
+            self.finish_vs_engine(Outcome::Draw, Termination::DrawAgreement);
+        } else {
+            game.draw_declined = true;
+        }
+    } //
+
+    /// Starts a `Game` between two loaded engines on its own thread, from the start position
+    /// of the current history, and follows it on the board. Both engines get fresh processes
+    /// with the options of the loaded ones, which keep analysing.
+    pub fn start_watch(&mut self, new_match: NewMatch, cx: &mut Context<Self>) {
+        let engines = &cx.global::<SharedState>().engines.engines;
+        let (Some(white), Some(black)) = (
+            engines.get(new_match.white_index).cloned(),
+            engines.get(new_match.black_index).cloned(),
+        ) else {
+            self.error_message = Some("Load an engine first".to_string());
+            return;
+        };
+        let task = cx.spawn(async move |this, cx: &mut AsyncApp| {
+            let started = cx
+                .background_spawn(async move {
+                    let mut sides = [white, black];
+                    for engine in &mut sides {
+                        engine
+                            .start()
+                            .map_err(|err| format!("Failed to start {}: {}", engine.name, err))?;
+                    }
+                    Ok::<_, String>(sides)
+                })
+                .await;
+            let _ = this.update(cx, |board, cx| {
+                match started {
+                    Ok(sides) => board.begin_watch(new_match, sides, cx),
+                    Err(err) => board.error_message = Some(err),
+                }
+                cx.notify();
+            });
+        });
+        task.detach();
+    } //
+
+    fn begin_watch(&mut self, new_match: NewMatch, sides: [Engine; 2], cx: &mut Context<Self>) {
+        let names = sides.each_ref().map(|engine| engine.name.clone());
+        let engines = &mut cx.global_mut::<SharedState>().engines;
+        if engines.is_analyzing {
+            engines.toggle_analyze(&self.board);
+        }
+
+        let time_control = TIME_CONTROLS[new_match.time_control_index].1;
+        let start_fen = self.start_fen.clone();
+        let (event_tx, event_rx) = mpsc::channel();
+        let (command_tx, command_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let [white, black] = sides;
+            let mut game = match start_fen {
+                Some(fen) => Game::from_fen(white, black, time_control, &fen),
+                None => Game::new(white, black, time_control),
+            };
+            game.watch(event_tx, command_rx);
+            game.play();
+        });
+
+        self.new_match = None;
+        self.vs_engine = None;
+        self.game_engine = None;
+        self.watching = None;
+        self.tree = GameTree::new();
+        self.go_to_node(ROOT);
+        self.is_board_flipped = false;
+        self.game_count += 1;
+        let id = self.game_count;
+        self.watching = Some(WatchedGame::new(id, names, time_control, event_rx, command_tx));
+
+        let task = cx.spawn(async move |this, cx: &mut AsyncApp| {
+            loop {
+                cx.background_executor().timer(Duration::from_millis(100)).await;
+                let is_running = this
+                    .update(cx, |board, cx| {
+                        let is_running = board.tick_watch(id);
+                        cx.notify();
+                        is_running
+                    })
+                    .unwrap_or(false);
+                if !is_running {
+                    break;
+                }
+            }
+        });
+        task.detach();
+    } //
+
+    /// Shows what the watched game sent since the last tick. Returns false once game `id`
+    /// is over or replaced.
+    fn tick_watch(&mut self, id: usize) -> bool {
+        let mut events = Vec::new();
+        match self.watching.as_mut().filter(|game| game.id == id) {
+            Some(game) => {
+                while let Some(event) = game.next_event() {
+                    events.push(event);
+                }
+            }
+            None => return false,
+        }
+
+        for event in events {
+            match &event {
+                GameEvent::Info { white, info } => {
+                    let san = notation::pv_to_san(&mut self.board, &info.pv);
+                    let pv = if san.len() == info.pv.len() {
+                        san.join(" ")
+                    } else {
+                        info.pv.join(" ")
+                    };
+                    let index = info.multipv.unwrap_or(1).max(1) as usize - 1;
+                    let line = WatchedLine {
+                        score: info.score,
+                        depth: info.depth,
+                        selective_depth: info.selective_depth,
+                        pv,
+                    };
+                    if let Some(game) = self.watching.as_mut() {
+                        game.set_line(*white, index, line);
+                    }
+                }
+                GameEvent::Move { record, .. } => {
+                    if let Some(mv) = notation::find_uci_move(&mut self.board, &record.uci) {
+                        self.play_move(mv.to_uci());
+                        self.animate_last_move();
+                    }
+                }
+                GameEvent::Turn { .. } | GameEvent::Finished(_) => {}
+            }
+            if let Some(game) = self.watching.as_mut() {
+                game.apply(&event);
+            }
+        }
+        self.watching.as_ref().is_some_and(|game| !game.is_over())
+    } //
+
+    /// UCI `position` command for the position shown
+    fn position_command(&self) -> String {
+        let position = match &self.start_fen {
+            Some(fen) => format!("position fen {}", fen),
+            None => "position startpos".to_string(),
+        };
+        let moves = self
+            .tree
+            .path(self.current_node)
+            .into_iter()
+            .filter_map(|node| self.tree.mv(node))
+            .map(|mv| mv.to_uci())
+            .collect::<Vec<_>>();
+        if moves.is_empty() {
+            format!("{}\n", position)
+        } else {
+            format!("{} moves {}\n", position, moves.join(" "))
+        }
+    } //
+
+    /// Ends a right-button drag: the same square toggles a circle, another one an arrow
+    pub fn finish_arrow(&mut self, to: usize) {
+        let Some(from) = self.arrow_start.take() else {
+            return;
+        };
+        if from == to {
+            match self.user_circles.iter().position(|&square| square == to) {
+                Some(index) => {
+                    self.user_circles.remove(index);
+                }
+                None => self.user_circles.push(to),
+            }
+        } else {
+            match self.user_arrows.iter().position(|&arrow| arrow == (from, to)) {
+                Some(index) => {
+                    self.user_arrows.remove(index);
+                }
+                None => self.user_arrows.push((from, to)),
+            }
+        }
+    } //
+
+    fn clear_drawings(&mut self) {
+        self.user_arrows.clear();
+        self.user_circles.clear();
     } //
 
     pub fn flip_board_visually(&mut self) {
@@ -171,23 +1016,862 @@ impl Board {
     } //
 }
 
+/// Vertical bar beside the board, White's share at White's side of the board
+fn eval_bar(score: Option<Score>, white_to_move: bool, is_board_flipped: bool, height: Pixels) -> Div {
+    let white_share = score.map_or(0.5, |score| score.white_share(white_to_move));
+    let label = score.map(|score| match score.for_white(white_to_move) {
+        Score::Cp(cp) => format!("{:+.1}", cp as f32 / 100.0),
+        Score::Mate(moves) => format!("M{}", moves.abs()),
+    });
+    let white_is_winning = white_share >= 0.5;
+
+    let segment = |is_white: bool, share: f32| {
+        div()
+            .w_full()
+            .h(height * share)
+            .flex()
+            .flex_col()
+            .items_center()
+            .justify_end()
+            .when(is_white, |this| this.bg(gpui::white()).text_color(gpui::black()))
+            .when(!is_white, |this| this.bg(gpui::black()).text_color(gpui::white()))
+            .when(is_board_flipped == is_white, |this| this.justify_start())
+            .when_some(label.clone().filter(|_| is_white == white_is_winning), |this, label| {
+                this.child(label)
+            })
+    };
+
+    // white is at the bottom unless the board is flipped
+    div()
+        .w(px(22.))
+        .h(height)
+        .flex()
+        .flex_none()
+        .overflow_hidden()
+        .rounded_sm()
+        .text_size(px(8.))
+        .when(!is_board_flipped, |this| this.flex_col())
+        .when(is_board_flipped, |this| this.flex_col_reverse())
+        .child(segment(false, 1.0 - white_share))
+        .child(segment(true, white_share))
+} //
+
+/// A square in setup mode: click paints with the brush, pieces can be dragged around or off
+fn setup_square(
+    i: usize,
+    piece: Option<usize>,
+    is_board_flipped: bool,
+    square_size: Pixels,
+    cx: &mut Context<Board>,
+) -> Div {
+    let element = board_square(i, None, None, piece, is_board_flipped, false, 64, &Vec::new(), None, None, None)
+        .id(ElementId::named_usize("setup_square", i))
+        .on_mouse_down(
+            MouseButton::Left,
+            cx.listener(move |board, _, _, cx| {
+                board.setup_click(i);
+                cx.notify();
+            }),
+        )
+        .when_some(piece, |this, piece| {
+            this.on_drag(
+                DraggedPiece {
+                    from: Some(i),
+                    piece,
+                    size: square_size,
+                },
+                |dragged, _, _, cx| cx.new(|_| dragged.clone()),
+            )
+        })
+        .on_drop(cx.listener(move |board, dragged: &DraggedPiece, _, cx| {
+            board.setup_drop(dragged, Some(i));
+            // the board itself takes drops that miss every square
+            cx.stop_propagation();
+            cx.notify();
+        }));
+    div().size_full().relative().child(element)
+} //
+
+/// Palette, side to move, castling rights, en passant and the setup actions
+fn setup_panel(setup: &Setup, brush: Option<usize>, square_size: Pixels, cx: &mut Context<Board>) -> Div {
+    let mut palette_item = |piece: Option<usize>| {
+        div()
+            .id(ElementId::named_usize("palette", piece.map_or(12, |piece| piece)))
+            .size(square_size * 0.6)
+            .p(px(2.))
+            .rounded_sm()
+            .cursor_pointer()
+            .hover(|this| this.bg(rgb(gui::colors::MUTED)))
+            .when(brush == piece, |this| this.bg(rgb(gui::colors::SQUARE_SELECTION)))
+            .child(match piece {
+                Some(piece) => img(Path::new(piece_image(piece))).size_full().into_any_element(),
+                None => div()
+                    .size_full()
+                    .flex()
+                    .items_center()
+                    .justify_center()
+                    .text_color(rgb(gui::colors::TEXT))
+                    .child("✕")
+                    .into_any_element(),
+            })
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |board, _, _, cx| {
+                    board.setup_brush = piece;
+                    cx.notify();
+                }),
+            )
+            .when_some(piece, |this, piece| {
+                this.on_drag(
+                    DraggedPiece {
+                        from: None,
+                        piece,
+                        size: square_size,
+                    },
+                    |dragged, _, _, cx| cx.new(|_| dragged.clone()),
+                )
+            })
+    };
+    let white_pieces = (0..6).map(|piece| palette_item(Some(piece))).collect::<Vec<_>>();
+    let black_pieces = (6..12).map(|piece| palette_item(Some(piece))).collect::<Vec<_>>();
+    let eraser = palette_item(None);
+
+    let castling = ["White O-O", "White O-O-O", "Black O-O", "Black O-O-O"]
+        .into_iter()
+        .enumerate()
+        .map(|(index, label)| {
+            div()
+                .flex()
+                .items_center()
+                .gap_1()
+                .child(check_box(setup.castling[index]).on_any_mouse_down(cx.listener(
+                    move |board, _, _, cx| {
+                        if let Some(setup) = &mut board.setup {
+                            setup.castling[index] = !setup.castling[index];
+                        }
+                        cx.notify();
+                    },
+                )))
+                .child(label)
+        })
+        .collect::<Vec<_>>();
+
+    let en_passant = setup.en_passant;
+    let en_passant_choices = std::iter::once(None)
+        .chain(setup.en_passant_candidates().into_iter().map(Some))
+        .map(|square| {
+            let label = square.map_or("-".to_string(), |square| {
+                format!("{}{}", (b'a' + (square % 8) as u8) as char, square / 8 + 1)
+            });
+            div()
+                .id(ElementId::named_usize("en_passant", square.unwrap_or(64)))
+                .px_1()
+                .rounded_sm()
+                .cursor_pointer()
+                .hover(|this| this.bg(rgb(gui::colors::MUTED)))
+                .when(en_passant == square, |this| {
+                    this.bg(rgb(gui::colors::SQUARE_SELECTION)).text_color(gpui::black())
+                })
+                .child(label)
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |board, _, _, cx| {
+                        if let Some(setup) = &mut board.setup {
+                            setup.en_passant = square;
+                        }
+                        cx.notify();
+                    }),
+                )
+        })
+        .collect::<Vec<_>>();
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_2()
+        .p_2()
+        .rounded_sm()
+        .bg(rgb(gui::colors::SECONDARY_BACKGROUND))
+        .text_xs()
+        .text_color(rgb(gui::colors::TEXT))
+        .child(div().flex().children(white_pieces))
+        .child(div().flex().children(black_pieces).child(eraser))
+        .child(
+            button(if setup.white_to_move { "White to move" } else { "Black to move" })
+                .on_any_mouse_down(cx.listener(|board, _, _, cx| {
+                    if let Some(setup) = &mut board.setup {
+                        setup.white_to_move = !setup.white_to_move;
+                        setup.en_passant = None;
+                    }
+                    cx.notify();
+                })),
+        )
+        .children(castling)
+        .child(
+            div()
+                .flex()
+                .flex_wrap()
+                .items_center()
+                .gap_1()
+                .child("En passant")
+                .children(en_passant_choices),
+        )
+        .child(
+            div()
+                .flex()
+                .flex_wrap()
+                .gap_1()
+                .child(button("Clear").on_any_mouse_down(cx.listener(|board, _, _, cx| {
+                    board.setup = Some(Setup::empty());
+                    cx.notify();
+                })))
+                .child(button("Start position").on_any_mouse_down(cx.listener(
+                    |board, _, _, cx| {
+                        board.setup = Some(Setup::start());
+                        cx.notify();
+                    },
+                )))
+                .child(button("Cancel").on_any_mouse_down(cx.listener(|board, _, _, cx| {
+                    board.setup = None;
+                    cx.notify();
+                })))
+                .child(button("Done").on_any_mouse_down(cx.listener(|board, _, _, cx| {
+                    board.finish_setup();
+                    cx.notify();
+                }))),
+        )
+} //
+
+/// One option of a group in the game dialogs, highlighted when selected
+fn choice_chip(group: &'static str, index: usize, label: String, selected: bool) -> Stateful<Div> {
+    div()
+        .id(ElementId::named_usize(group, index))
+        .px_1()
+        .rounded_sm()
+        .cursor_pointer()
+        .hover(|this| this.bg(rgb(gui::colors::MUTED)))
+        .when(selected, |this| {
+            this.bg(rgb(gui::colors::SQUARE_SELECTION)).text_color(gpui::black())
+        })
+        .child(label)
+} //
+
+/// The games of a multi-game PGN file, clicking one shows it
+fn pgn_games_panel(games: &[pgn::PgnGame], selected: usize, cx: &mut Context<Board>) -> Stateful<Div> {
+    let rows = games
+        .iter()
+        .enumerate()
+        .map(|(index, game)| {
+            let label = format!(
+                "{}. {} - {} {}",
+                index + 1,
+                game.tag("White").unwrap_or("?"),
+                game.tag("Black").unwrap_or("?"),
+                game.result
+            );
+            choice_chip("pgn_game", index, label, index == selected)
+                .truncate()
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |board, _, _, cx| {
+                        board.load_pgn_file_game(index);
+                        cx.notify();
+                    }),
+                )
+        })
+        .collect::<Vec<_>>();
+
+    div()
+        .id("pgn_games")
+        .overflow_y_scroll()
+        .max_h(px(120.))
+        .flex_none()
+        .flex()
+        .flex_col()
+        .p_1()
+        .rounded_sm()
+        .bg(rgb(gui::colors::SECONDARY_BACKGROUND))
+        .text_xs()
+        .text_color(rgb(gui::colors::TEXT))
+        .child(format!("{} games in file", games.len()))
+        .children(rows)
+} //
+
+/// Colour, engine and time control for a game against an engine
+fn new_game_panel(new_game: NewGame, engine_names: Vec<String>, cx: &mut Context<Board>) -> Div {
+    let mut choice = |group: &'static str, index: usize, label: String, selected: bool, choose: fn(&mut NewGame, usize)| {
+        choice_chip(group, index, label, selected).on_mouse_down(
+            MouseButton::Left,
+            cx.listener(move |board, _, _, cx| {
+                if let Some(new_game) = &mut board.new_game {
+                    choose(new_game, index);
+                }
+                cx.notify();
+            }),
+        )
+    };
+
+    let colours = ["White", "Black"]
+        .into_iter()
+        .enumerate()
+        .map(|(index, label)| {
+            choice("play_as", index, label.to_string(), new_game.user_is_white == (index == 0), |new_game, index| {
+                new_game.user_is_white = index == 0;
+            })
+        })
+        .collect::<Vec<_>>();
+    let has_engines = !engine_names.is_empty();
+    let engines = engine_names
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| {
+            choice("play_engine", index, name, new_game.engine_index == index, |new_game, index| {
+                new_game.engine_index = index;
+            })
+        })
+        .collect::<Vec<_>>();
+    let time_controls = TIME_CONTROLS
+        .iter()
+        .enumerate()
+        .map(|(index, (label, _))| {
+            choice("play_time_control", index, label.to_string(), new_game.time_control_index == index, |new_game, index| {
+                new_game.time_control_index = index;
+            })
+        })
+        .collect::<Vec<_>>();
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_2()
+        .p_2()
+        .rounded_sm()
+        .bg(rgb(gui::colors::SECONDARY_BACKGROUND))
+        .text_xs()
+        .text_color(rgb(gui::colors::TEXT))
+        .child("Play as")
+        .child(div().flex().flex_wrap().gap_1().children(colours))
+        .child("Engine")
+        .child(
+            div()
+                .flex()
+                .flex_col()
+                .gap_1()
+                .children(engines)
+                .when(!has_engines, |this| {
+                    this.text_color(rgb(gui::colors::MUTED)).child("No engines loaded")
+                }),
+        )
+        .child("Time control")
+        .child(div().flex().flex_wrap().gap_1().children(time_controls))
+        .when(new_game.is_confirming, |this| {
+            this.child("The moves on the board will be discarded")
+        })
+        .child(
+            div()
+                .flex()
+                .flex_wrap()
+                .gap_1()
+                .child(button("Cancel").on_any_mouse_down(cx.listener(|board, _, _, cx| {
+                    board.new_game = None;
+                    cx.notify();
+                })))
+                .child(
+                    button(if new_game.is_confirming { "Discard and start" } else { "Start" })
+                        .on_any_mouse_down(cx.listener(|board, _, _, cx| {
+                            let has_moves = board.has_moves();
+                            match &mut board.new_game {
+                                Some(new_game) if has_moves && !new_game.is_confirming => {
+                                    new_game.is_confirming = true;
+                                }
+                                Some(new_game) => {
+                                    let new_game = *new_game;
+                                    board.start_vs_engine(new_game, cx);
+                                }
+                                None => {}
+                            }
+                            cx.notify();
+                        })),
+                ),
+        )
+} //
+
+/// Clocks, status and the resign and draw buttons of a game against an engine
+fn game_panel(game: &VsEngine, cx: &mut Context<Board>) -> Div {
+    let players = game.players();
+    // the opponent's clock on top, as on the board
+    let clocks = [!game.user_is_white, game.user_is_white]
+        .into_iter()
+        .map(|white| {
+            let is_running = white == game.white_to_move() && !game.is_over();
+            div()
+                .flex()
+                .justify_between()
+                .px_2()
+                .rounded_sm()
+                .when(is_running, |this| {
+                    this.bg(rgb(gui::colors::SQUARE_SELECTION)).text_color(gpui::black())
+                })
+                .child(players[if white { 0 } else { 1 }].to_string())
+                .child(game.clock(white).map_or("-".to_string(), pgn::clock_text))
+        })
+        .collect::<Vec<_>>();
+
+    let status = match game.result {
+        Some((outcome, termination)) => {
+            format!("{} by {}", outcome.result_string(), termination)
+        }
+        None if game.draw_declined => "Draw offer declined".to_string(),
+        None if game.is_engine_turn() => format!("{} is thinking", game.engine_name),
+        None => "Your move".to_string(),
+    };
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .p_1()
+        .rounded_sm()
+        .bg(rgb(gui::colors::SECONDARY_BACKGROUND))
+        .text_sm()
+        .text_color(rgb(gui::colors::TEXT))
+        .children(clocks)
+        .child(
+            div()
+                .flex()
+                .items_center()
+                .justify_between()
+                .gap_1()
+                .text_xs()
+                .child(status)
+                .when(!game.is_over(), |this| {
+                    this.child(
+                        div()
+                            .flex()
+                            .gap_1()
+                            .child(logo_button("svg/forfeit.svg", 6.).on_any_mouse_down(
+                                cx.listener(|board, _, _, cx| {
+                                    board.resign();
+                                    cx.notify();
+                                }),
+                            ))
+                            .child(logo_button("svg/half.svg", 6.).on_any_mouse_down(
+                                cx.listener(|board, _, _, cx| {
+                                    board.offer_draw();
+                                    cx.notify();
+                                }),
+                            )),
+                    )
+                }),
+        )
+} //
+
+/// White and black engines and the time control for an engine game to watch
+fn new_match_panel(new_match: NewMatch, engine_names: Vec<String>, cx: &mut Context<Board>) -> Div {
+    let mut choice = |group: &'static str, index: usize, label: String, selected: bool, choose: fn(&mut NewMatch, usize)| {
+        choice_chip(group, index, label, selected).on_mouse_down(
+            MouseButton::Left,
+            cx.listener(move |board, _, _, cx| {
+                if let Some(new_match) = &mut board.new_match {
+                    choose(new_match, index);
+                }
+                cx.notify();
+            }),
+        )
+    };
+
+    let white_engines = engine_names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            choice("watch_white", index, name.clone(), new_match.white_index == index, |new_match, index| {
+                new_match.white_index = index;
+            })
+        })
+        .collect::<Vec<_>>();
+    let black_engines = engine_names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| {
+            choice("watch_black", index, name.clone(), new_match.black_index == index, |new_match, index| {
+                new_match.black_index = index;
+            })
+        })
+        .collect::<Vec<_>>();
+    let time_controls = TIME_CONTROLS
+        .iter()
+        .enumerate()
+        .map(|(index, (label, _))| {
+            choice("watch_time_control", index, label.to_string(), new_match.time_control_index == index, |new_match, index| {
+                new_match.time_control_index = index;
+            })
+        })
+        .collect::<Vec<_>>();
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_2()
+        .p_2()
+        .rounded_sm()
+        .bg(rgb(gui::colors::SECONDARY_BACKGROUND))
+        .text_xs()
+        .text_color(rgb(gui::colors::TEXT))
+        .when(engine_names.is_empty(), |this| {
+            this.child(div().text_color(rgb(gui::colors::MUTED)).child("No engines loaded"))
+        })
+        .child("White")
+        .child(div().flex().flex_col().gap_1().children(white_engines))
+        .child("Black")
+        .child(div().flex().flex_col().gap_1().children(black_engines))
+        .child("Time control")
+        .child(div().flex().flex_wrap().gap_1().children(time_controls))
+        .when(new_match.is_confirming, |this| {
+            this.child("The moves on the board will be discarded")
+        })
+        .child(
+            div()
+                .flex()
+                .flex_wrap()
+                .gap_1()
+                .child(button("Cancel").on_any_mouse_down(cx.listener(|board, _, _, cx| {
+                    board.new_match = None;
+                    cx.notify();
+                })))
+                .child(
+                    button(if new_match.is_confirming { "Discard and start" } else { "Start" })
+                        .on_any_mouse_down(cx.listener(|board, _, _, cx| {
+                            let has_moves = board.has_moves();
+                            match &mut board.new_match {
+                                Some(new_match) if has_moves && !new_match.is_confirming => {
+                                    new_match.is_confirming = true;
+                                }
+                                Some(new_match) => {
+                                    let new_match = *new_match;
+                                    board.start_watch(new_match, cx);
+                                }
+                                None => {}
+                            }
+                            cx.notify();
+                        })),
+                ),
+        )
+} //
+
+/// Clocks of a watched game with pause, abort and adjudication controls
+fn watch_panel(game: &WatchedGame, is_board_flipped: bool, cx: &mut Context<Board>) -> Div {
+    // the clock of the side at the top of the board first
+    let clocks = [is_board_flipped, !is_board_flipped]
+        .into_iter()
+        .map(|white| {
+            let is_running = game.thinking() == Some(white);
+            div()
+                .flex()
+                .justify_between()
+                .gap_1()
+                .px_2()
+                .rounded_sm()
+                .when(is_running, |this| {
+                    this.bg(rgb(gui::colors::SQUARE_SELECTION)).text_color(gpui::black())
+                })
+                .child(div().truncate().child(game.names[if white { 0 } else { 1 }].clone()))
+                .child(game.clock(white).map_or("-".to_string(), pgn::clock_text))
+        })
+        .collect::<Vec<_>>();
+
+    let status = match game.result {
+        Some((outcome, termination)) => {
+            format!("{} by {}", outcome.result_string(), termination)
+        }
+        // the game holds once the engine to move has answered
+        None if game.is_paused => "Paused".to_string(),
+        None => "Playing".to_string(),
+    };
+    let command = |command: GameCommand| {
+        move |board: &mut Board, _: &gpui::MouseDownEvent, _: &mut Window, cx: &mut Context<Board>| {
+            if let Some(game) = &mut board.watching {
+                game.send(command);
+            }
+            cx.notify();
+        }
+    };
+
+    div()
+        .flex()
+        .flex_col()
+        .gap_1()
+        .p_1()
+        .rounded_sm()
+        .bg(rgb(gui::colors::SECONDARY_BACKGROUND))
+        .text_sm()
+        .text_color(rgb(gui::colors::TEXT))
+        .children(clocks)
+        .child(div().text_xs().child(status))
+        .when(!game.is_over(), |this| {
+            this.child(
+                div()
+                    .flex()
+                    .flex_wrap()
+                    .gap_1()
+                    .text_xs()
+                    .child(if game.is_paused {
+                        button("Resume").on_any_mouse_down(cx.listener(command(GameCommand::Resume)))
+                    } else {
+                        button("Pause").on_any_mouse_down(cx.listener(command(GameCommand::Pause)))
+                    })
+                    .child(button("Abort").on_any_mouse_down(cx.listener(command(GameCommand::Abort))))
+                    .child(button("1-0").on_any_mouse_down(cx.listener(command(
+                        GameCommand::Adjudicate(Outcome::WhiteWin),
+                    ))))
+                    .child(button("½-½").on_any_mouse_down(cx.listener(command(
+                        GameCommand::Adjudicate(Outcome::Draw),
+                    ))))
+                    .child(button("0-1").on_any_mouse_down(cx.listener(command(
+                        GameCommand::Adjudicate(Outcome::BlackWin),
+                    )))),
+            )
+        })
+} //
+
+fn open_fen_window(cx: &mut App) {
+    let bounds = Bounds::centered(None, size(px(500.), px(150.)), cx);
+    let options = WindowOptions {
+        window_bounds: Some(WindowBounds::Windowed(bounds)),
+        ..Default::default()
+    };
+
+    let text_input = cx.new(|cx| InputField::new(cx));
+    let input_controller = cx.new(|cx| InputController {
+        recent_keystrokes: Vec::new(),
+        focus_handle: cx.focus_handle(),
+        text_input,
+    });
+
+    let window = cx
+        .open_window(options, |_, cx| {
+            cx.new(|cx| FenWindow {
+                input_controller,
+                focus_handle: cx.focus_handle(),
+            })
+        })
+        .unwrap();
+
+    let view = window.update(cx, |_, _, cx| cx.entity()).unwrap();
+    cx.observe_keystrokes(move |_, ev, _, cx| {
+        view.update(cx, |view, cx| {
+            view.input_controller
+                .as_mut(cx)
+                .recent_keystrokes
+                .push(ev.keystroke.clone());
+            cx.notify();
+        })
+    })
+    .detach();
+    cx.on_keyboard_layout_change({
+        move |cx| {
+            window.update(cx, |_, _, cx| cx.notify()).ok();
+        }
+    })
+    .detach();
+} //
+
+/// One row of the move list, moves are (tree node, text)
+enum MoveListRow {
+    Pair {
+        number: usize,
+        white: Option<(usize, String)>,
+        black: Option<(usize, String)>,
+    },
+    Variation {
+        depth: usize,
+        moves: Vec<(usize, String)>,
+    },
+}
+
+/// The four promotion pieces stacked from the promotion square towards the middle of the board
+fn promotion_picker(
+    square: usize,
+    is_white: bool,
+    is_board_flipped: bool,
+    square_size: Pixels,
+    cx: &mut Context<Board>,
+) -> impl IntoElement {
+    let offset = if is_white { 0 } else { 6 };
+    let pieces = [('q', 4), ('n', 1), ('r', 3), ('b', 2)]
+        .into_iter()
+        .map(|(letter, piece)| {
+            div()
+                .size(square_size)
+                .p(px(2.))
+                .cursor_pointer()
+                .hover(|this| this.bg(rgb(gui::colors::MUTED)))
+                .child(img(Path::new(piece_image(piece + offset))).size_full())
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |board, _, _, cx| {
+                        board.promote(letter);
+                        cx.notify();
+                    }),
+                )
+        })
+        .collect::<Vec<_>>();
+
+    // the promotion rank is at the top of the screen unless the board is flipped
+    let is_top_edge = (square / 8 == 7) != is_board_flipped;
+    deferred(
+        div()
+            .absolute()
+            .left_0()
+            .when(is_top_edge, |this| this.top_0().flex_col())
+            .when(!is_top_edge, |this| this.bottom_0().flex_col_reverse())
+            .flex()
+            .w(square_size)
+            .bg(rgb(gui::colors::SECONDARY_BACKGROUND))
+            .border_1()
+            .border_color(rgb(gui::colors::MUTED))
+            .shadow_lg()
+            .occlude()
+            .children(pieces)
+            .on_mouse_down_out(cx.listener(|board, _, _, cx| {
+                board.pending_promotion = None;
+                cx.notify();
+            })),
+    )
+    .priority(1)
+} //
+
 impl Render for Board {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let animation_duration = self.settings.animation_duration();
+        if self
+            .animation
+            .as_ref()
+            .is_some_and(|animation| animation.started.elapsed() >= animation_duration)
+        {
+            self.animation = None;
+        }
         let global = cx.global_mut::<SharedState>();
         if let Some(fen) = global.fen_string.clone() {
             self.load_from_fen(fen.to_string());
             global.fen_string = None;
         }
         global.engines.poll_engines();
+        // the loaded engines' last lines would give moves away during a game against an engine
+        let hides_analysis = self.vs_engine.as_ref().is_some_and(|game| !game.is_over());
+        // lines found for another position are not drawn on this one or scored for its side to move
+        let is_analysis_current = global.engines.analysis_fen.as_deref() == Some(self.board.to_fen().as_str());
+
+        // best move, and optionally the second MultiPV move, of every engine shown
+        let mut arrows = Vec::new();
+        let shown_engines = global
+            .engines
+            .engines
+            .iter()
+            .filter(|engine| engine.is_show && !hides_analysis && is_analysis_current);
+        for (shown, engine) in shown_engines.enumerate() {
+            let color: Hsla = rgb(gui::colors::ENGINE_ARROWS[shown % gui::colors::ENGINE_ARROWS.len()]).into();
+            let line_count = if self.show_second_arrow { 2 } else { 1 };
+            for (index, line) in engine.lines.iter().take(line_count).enumerate() {
+                let mv = line
+                    .pv
+                    .first()
+                    .and_then(|uci| notation::find_uci_move(&mut self.board, uci));
+                if let Some(mv) = mv {
+                    arrows.push(BoardArrow {
+                        from: mv.from(),
+                        to: mv.to(),
+                        color: color.opacity(if index == 0 { 0.8 } else { 0.45 }),
+                        width: if index == 0 { 0.18 } else { 0.12 },
+                    });
+                }
+            }
+        }
+        let user_color: Hsla = rgb(gui::colors::USER_ARROW).into();
+        arrows.extend(self.user_arrows.iter().map(|&(from, to)| BoardArrow {
+            from,
+            to,
+            color: user_color.opacity(0.8),
+            width: 0.18,
+        }));
+        let circles = self
+            .user_circles
+            .iter()
+            .map(|&square| (square, user_color.opacity(0.8)))
+            .collect::<Vec<_>>();
+
+        // the eval bar follows the best line of the picked engine
+        let eval_score = global
+            .engines
+            .engines
+            .get(self.eval_engine)
+            .filter(|engine| engine.is_show && !hides_analysis && is_analysis_current)
+            .and_then(|engine| engine.lines.first())
+            .and_then(|line| line.score);
 
-        let analysis = global
+        // lines are written as SAN in the position they were found for, which the board may have left
+        let mut analysis_board = global.engines.analysis_fen.as_ref().map(|fen| {
+            let mut board = QueenFishBoard::new();
+            board.load_from_fen(&format!("{} 0 1", fen));
+            board
+        });
+        // snapshot what the panel needs so the global borrow ends before the listeners are built
+        let engines_analysis = global
             .engines
             .engines
             .iter()
-            .filter(|engine| engine.is_show)
-            .map(|engine| {
+            .enumerate()
+            .filter(|(_, engine)| engine.is_show && !hides_analysis)
+            .map(|(index, engine)| {
+                let lines = engine
+                    .lines
+                    .iter()
+                    .map(|line| {
+                        let san = analysis_board
+                            .as_mut()
+                            .map(|board| notation::pv_to_san(board, &line.pv))
+                            .unwrap_or_default();
+                        let pv = if san.len() == line.pv.len() {
+                            san.join(" ")
+                        } else {
+                            line.pv.join(" ")
+                        };
+                        (line.score, line.depth, line.selective_depth, pv)
+                    })
+                    .collect::<Vec<_>>();
+                (
+                    index,
+                    engine.name.clone(),
+                    engine.supports_multipv(),
+                    engine.multipv(),
+                    engine.max_multipv(),
+                    lines,
+                )
+            })
+            .collect::<Vec<_>>();
+        // a watched game shows its two engines instead of the loaded ones
+        let engines_analysis = match &self.watching {
+            Some(game) => ["White", "Black"]
+                .into_iter()
+                .zip(&game.names)
+                .zip(&game.lines)
+                .enumerate()
+                .map(|(index, ((side, name), lines))| {
+                    let lines = lines
+                        .iter()
+                        .map(|line| (line.score, line.depth, line.selective_depth, line.pv.clone()))
+                        .collect::<Vec<_>>();
+                    (index, format!("{}: {}", side, name), false, 1, 1, lines)
+                })
+                .collect(),
+            None => engines_analysis,
+        };
+
+        let eval_engine = self.watching.is_none().then_some(self.eval_engine);
+        let analysis = engines_analysis
+            .into_iter()
+            .map(|(index, name, supports_multipv, multipv, max_multipv, lines)| {
                 return div()
-                    .id(ElementId::named_usize(engine.name.clone(), 0))
+                    .id(ElementId::named_usize(name.clone(), 0))
                     .overflow_y_scroll()
                     .w_full()
                     .flex_1()
@@ -197,7 +1881,57 @@ impl Render for Board {
                     .py_1()
                     .px_4()
                     .text_color(gpui::white())
-                    .child(div().child(engine.name.clone()))
+                    .child(
+                        div()
+                            .flex()
+                            .items_center()
+                            .justify_between()
+                            .child(
+                                div()
+                                    .child(name.clone())
+                                    .when(eval_engine == Some(index), |this| this.underline())
+                                    .when(eval_engine.is_some(), |this| {
+                                        this.cursor_pointer().on_mouse_down(
+                                            MouseButton::Left,
+                                            cx.listener(move |board, _, _, cx| {
+                                                board.eval_engine = index;
+                                                cx.notify();
+                                            }),
+                                        )
+                                    }),
+                            )
+                            .when(supports_multipv, |this| {
+                                this.child(
+                                    div()
+                                        .flex()
+                                        .items_center()
+                                        .gap_1()
+                                        .text_xs()
+                                        .child("MultiPV")
+                                        .child(button("-").on_any_mouse_down(cx.listener(
+                                            move |board, _, _, cx| {
+                                                if multipv > 1 {
+                                                    cx.global_mut::<SharedState>()
+                                                        .engines
+                                                        .set_multipv(index, multipv - 1);
+                                                    cx.notify();
+                                                }
+                                            },
+                                        )))
+                                        .child(multipv.to_string())
+                                        .child(button("+").on_any_mouse_down(cx.listener(
+                                            move |board, _, _, cx| {
+                                                if multipv < max_multipv {
+                                                    cx.global_mut::<SharedState>()
+                                                        .engines
+                                                        .set_multipv(index, multipv + 1);
+                                                    cx.notify();
+                                                }
+                                            },
+                                        ))),
+                                )
+                            }),
+                    )
                     .child(seperator(gui::colors::MUTED))
                     .child(
                         div()
@@ -205,13 +1939,7 @@ impl Render for Board {
                             .child(
                                 div().child(
                                     div().flex().children(
-                                        [
-                                            ("Depth", 50),
-                                            ("Score", 100),
-                                            ("Nodes", 80),
-                                            ("Time", 80),
-                                            ("Best Move", 100),
-                                        ]
+                                        [("Score", 100), ("Depth", 60)]
                                         .iter()
                                         .map(|x| {
                                             div()
@@ -228,66 +1956,50 @@ impl Render for Board {
                                                 .text_color(gpui::white())
                                                 .border_r_1()
                                                 .border_color(rgb(gui::colors::MUTED))
-                                        }),
+                                        })
+                                        .chain([div().px_2().child("Line")]),
                                     ),
                                 ),
                             )
-                            // .when(!self.is_analyzing, |this| this.hidden())
-                            .children(engine.analysis.iter().rev().map(|x| match x {
-                                AnalysisLine::Move(m) => {
-                                    return div()
-                                        .flex()
-                                        .flex_row()
-                                        .gap_2()
-                                        .items_center()
-                                        .child(format!("Best Move: {}", m))
-                                        .text_color(gpui::white());
-                                }
-                                AnalysisLine::Depth {
-                                    depth,
-                                    score,
-                                    best_move,
-                                    nodes,
-                                    selective_depth,
-                                    time,
-                                } => {
-                                    let score_text: Option<String> = match score {
-                                        Some(Score::Cp(cp)) => Some(format!("{} cp", cp)),
-                                        Some(Score::Mate(m)) => Some(format!("Mate in {}", m)),
-                                        None => None,
+                            .children(lines.into_iter().map(
+                                |(score, depth, selective_depth, pv)| {
+                                    let score_text = match score {
+                                        Some(Score::Cp(cp)) => format!("{} cp", cp),
+                                        Some(Score::Mate(m)) => format!("Mate in {}", m),
+                                        None => String::new(),
                                     };
-                                    div().child(
-                                        div().flex().children(
-                                            [
-                                                (depth, 50),
-                                                (&score_text, 100),
-                                                (nodes, 80),
-                                                (time, 80),
-                                                (selective_depth, 20),
-                                                (&best_move, 100),
-                                            ]
-                                            .iter()
-                                            .filter(|x| x.0.is_some())
+                                    let depth_text = match (depth, selective_depth) {
+                                        (Some(depth), Some(selective_depth)) => {
+                                            format!("{}/{}", depth, selective_depth)
+                                        }
+                                        (Some(depth), None) => depth.to_string(),
+                                        _ => String::new(),
+                                    };
+                                    div().flex().children(
+                                        [(score_text, 100), (depth_text, 60)]
+                                            .into_iter()
                                             .map(|x| {
                                                 div()
                                                     .flex()
-                                                    .flex_row()
-                                                    .gap_2()
-                                                    .items_center()
+                                                    .flex_none()
                                                     .w(px(x.1 as f32))
                                                     .px_2()
-                                                    .flex()
                                                     .items_center()
                                                     .justify_center()
-                                                    .child(x.0.clone().unwrap())
+                                                    .child(x.0)
                                                     .text_color(gpui::white())
                                                     .border_r_1()
                                                     .border_color(rgb(gui::colors::MUTED))
-                                            }),
-                                        ),
+                                            })
+                                            .chain([div()
+                                                .flex_1()
+                                                .min_w_0()
+                                                .px_2()
+                                                .truncate()
+                                                .child(pv)]),
                                     )
-                                }
-                            })),
+                                },
+                            )),
                     );
             })
             .collect::<Vec<_>>();
@@ -326,6 +2038,145 @@ impl Render for Board {
             queenfish::board::Turn::BLACK => self.board.black_king_sq(),
         };
 
+        let window_bounds = _window.window_bounds().get_bounds().size;
+        let window_width = window_bounds.width;
+        let window_height = window_bounds.height;
+        let board_size = window_width.min(window_height) * 0.5;
+        let is_white_to_move = matches!(self.board.turn, queenfish::board::Turn::WHITE);
+        let is_dragging = cx.has_active_drag();
+        let last_move = self.tree.mv(self.current_node).map(|mv| (mv.from(), mv.to()));
+
+        let current_node = self.current_node;
+        let rows = self.move_list_rows();
+        let current_row = rows.iter().position(|row| match row {
+            MoveListRow::Pair { white, black, .. } => [white, black]
+                .into_iter()
+                .flatten()
+                .any(|(node, _)| *node == current_node),
+            MoveListRow::Variation { moves, .. } => moves.iter().any(|(node, _)| *node == current_node),
+        });
+        if self.move_list_scrolled_to != current_node {
+            self.move_list_scroll.scroll_to_item(current_row.unwrap_or(0));
+            self.move_list_scrolled_to = current_node;
+        }
+
+        let mut move_cell = |node: usize, text: String| {
+            div()
+                .id(ElementId::named_usize("move", node))
+                .px_1()
+                .rounded_sm()
+                .cursor_pointer()
+                .hover(|this| this.bg(rgb(gui::colors::MUTED)))
+                .when(node == current_node, |this| {
+                    this.bg(rgb(gui::colors::SQUARE_SELECTION))
+                        .text_color(gpui::black())
+                })
+                .child(text)
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |board, _, _, cx| {
+                        board.go_to_node(node);
+                        cx.notify();
+                    }),
+                )
+        };
+        let move_rows = rows
+            .into_iter()
+            .map(|row| match row {
+                MoveListRow::Pair {
+                    number,
+                    white,
+                    black,
+                } => div()
+                    .flex()
+                    .gap_1()
+                    .child(
+                        div()
+                            .w(px(28.))
+                            .text_color(rgb(gui::colors::MUTED))
+                            .child(format!("{}.", number)),
+                    )
+                    .child(div().flex_1().child(match white {
+                        Some((node, san)) => move_cell(node, san).into_any_element(),
+                        None => div().px_1().child("...").into_any_element(),
+                    }))
+                    .child(div().flex_1().when_some(black, |this, (node, san)| {
+                        this.child(move_cell(node, san))
+                    })),
+                MoveListRow::Variation { depth, moves } => div()
+                    .flex()
+                    .flex_wrap()
+                    .pl(px(12. * depth as f32))
+                    .text_xs()
+                    .text_color(rgb(gui::colors::MUTED))
+                    .child("(")
+                    .children(moves.into_iter().map(|(node, text)| move_cell(node, text)))
+                    .child(")"),
+            })
+            .collect::<Vec<_>>();
+        let has_move = current_node != ROOT;
+        let eval_bar = eval_bar(eval_score, is_white_to_move, self.is_board_flipped, board_size);
+        let is_setup = self.setup.is_some();
+        let setup_controls = self
+            .setup
+            .clone()
+            .map(|setup| setup_panel(&setup, self.setup_brush, board_size / 8., cx).w(px(180.)));
+        let engine_names = cx
+            .global::<SharedState>()
+            .engines
+            .engines
+            .iter()
+            .map(|engine| engine.name.clone())
+            .collect::<Vec<_>>();
+        let new_game_controls = self
+            .new_game
+            .filter(|_| !is_setup)
+            .map(|new_game| new_game_panel(new_game, engine_names.clone(), cx).w(px(180.)));
+        let new_match_controls = self
+            .new_match
+            .filter(|_| !is_setup && new_game_controls.is_none())
+            .map(|new_match| new_match_panel(new_match, engine_names, cx).w(px(180.)));
+        let is_new_game = new_game_controls.is_some() || new_match_controls.is_some();
+        let pgn_games_controls = (!self.pgn_games.is_empty())
+            .then(|| pgn_games_panel(&self.pgn_games, self.pgn_game_index, cx));
+        let game_controls = match (&self.vs_engine, &self.watching) {
+            (Some(game), _) => Some(game_panel(game, cx)),
+            (None, Some(game)) => Some(watch_panel(game, self.is_board_flipped, cx)),
+            (None, None) => None,
+        };
+
+        // the sliding piece is drawn over the board, its target square stays empty until it lands
+        let animated_square = self.animation.as_ref().map(|animation| animation.to);
+        let animation_overlay = self.animation.as_ref().map(|animation| {
+            let square_size = board_size / 8.;
+            let is_board_flipped = self.is_board_flipped;
+            let origin = move |square: usize| {
+                let (column, row) = if is_board_flipped {
+                    (7 - square % 8, square / 8)
+                } else {
+                    (square % 8, 7 - square / 8)
+                };
+                (square_size * column as f32, square_size * row as f32)
+            };
+            let (from_x, from_y) = origin(animation.from);
+            let (to_x, to_y) = origin(animation.to);
+            div()
+                .absolute()
+                .left(from_x)
+                .top(from_y)
+                .size(square_size)
+                .p(px(2.))
+                .child(img(Path::new(piece_image(animation.piece))).size_full())
+                .with_animation(
+                    ElementId::named_usize("move_animation", animation.id),
+                    Animation::new(animation_duration).with_easing(ease_in_out),
+                    move |this, delta| {
+                        this.left(from_x + (to_x - from_x) * delta)
+                            .top(from_y + (to_y - from_y) * delta)
+                    },
+                )
+        });
+
         let mut squares = (0..64)
             .collect::<Vec<_>>()
             .chunks(8)
@@ -333,30 +2184,119 @@ impl Render for Board {
             .flatten()
             .copied()
             .map(|i| {
-                let mut element = board_square(i, self.selected_square, self.board.piece_at[i], self.is_board_flipped, is_king_in_check, current_turn_king_sq, &self.available_moves, winning_tag_index, losing_tag_index, draw_tag_index);
+                if let Some(setup) = &self.setup {
+                    return setup_square(i, setup.pieces[i], self.is_board_flipped, board_size / 8., cx);
+                }
 
-                element = element.on_mouse_down(
-                    gpui::MouseButton::Left,
-                    cx.listener(move |board, _event, _window, cx| {
-                        board.select_square(i);
+                // the dragged piece is drawn under the cursor instead of on its square
+                let piece = self.board.piece_at[i]
+                    .map(|piece| piece as usize)
+                    .filter(|_| !(is_dragging && self.selected_square == Some(i)))
+                    .filter(|_| animated_square != Some(i));
+                let mut element = board_square(i, self.selected_square, last_move, piece, self.is_board_flipped, is_king_in_check, current_turn_king_sq, &self.available_moves, winning_tag_index, losing_tag_index, draw_tag_index)
+                    .id(ElementId::named_usize("square", i));
+
+                element = element
+                    .on_mouse_down(
+                        gpui::MouseButton::Left,
+                        cx.listener(move |board, _event, _window, cx| {
+                            board.select_square(i);
+                            cx.notify();
+                        }),
+                    )
+                    .on_mouse_down(
+                        gpui::MouseButton::Right,
+                        cx.listener(move |board, _event, _window, _cx| {
+                            board.arrow_start = Some(i);
+                        }),
+                    )
+                    .on_mouse_up(
+                        gpui::MouseButton::Right,
+                        cx.listener(move |board, _event, _window, cx| {
+                            board.finish_arrow(i);
+                            cx.notify();
+                        }),
+                    );
+
+                let own_piece = self.board.piece_at[i]
+                    .map(|piece| piece as usize)
+                    .filter(|piece| (*piece < 6) == is_white_to_move);
+                element = element
+                    .when_some(own_piece, |this, piece| {
+                        this.on_drag(
+                            DraggedPiece {
+                                from: Some(i),
+                                piece,
+                                size: board_size / 8.,
+                            },
+                            |dragged, _, _, cx| cx.new(|_| dragged.clone()),
+                        )
+                    })
+                    .on_drop(cx.listener(move |board, dragged: &DraggedPiece, _, cx| {
+                        if let Some(from) = dragged.from {
+                            board.drop_piece(from, i);
+                        }
                         cx.notify();
-                    }),
-                );
+                    }));
 
-                return element;
+                let promotion = self.pending_promotion.filter(|(_, to)| *to == i);
+                return div()
+                    .size_full()
+                    .relative()
+                    .child(element)
+                    .when_some(promotion, |this, _| {
+                        this.child(promotion_picker(i, is_white_to_move, self.is_board_flipped, board_size / 8., cx))
+                    });
             })
             .collect::<Vec<_>>();
         if self.is_board_flipped {
             squares.reverse();
         }
 
-        let window_bounds = _window.window_bounds().get_bounds().size;
-        let window_width = window_bounds.width;
-        let window_height = window_bounds.height;
-        let board_size = window_width.min(window_height) * 0.5;
-
         div()
             .id("board")
+            .key_context(BOARD_CONTEXT)
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(|board, _: &PreviousMove, _, cx| {
+                board.undo_move();
+                cx.notify();
+            }))
+            .on_action(cx.listener(|board, _: &NextMove, _, cx| {
+                board.move_forward();
+                cx.notify();
+            }))
+            .on_action(cx.listener(|board, _: &FirstMove, _, cx| {
+                board.go_to_move(0);
+                cx.notify();
+            }))
+            .on_action(cx.listener(|board, _: &LastMove, _, cx| {
+                board.go_to_move(board.make_move_history.len());
+                cx.notify();
+            }))
+            .on_action(cx.listener(|board, _: &FlipBoard, _, cx| {
+                board.flip_board_visually();
+                cx.notify();
+            }))
+            .on_action(cx.listener(|board, _: &ToggleAnalysis, _, cx| {
+                if board.is_playing() {
+                    return;
+                }
+                cx.global_mut::<SharedState>()
+                    .engines
+                    .toggle_analyze(&board.board);
+                cx.notify();
+            }))
+            .on_action(cx.listener(|board, _: &ResetBoard, _, cx| {
+                board.reset_board();
+                cx.notify();
+            }))
+            .on_action(cx.listener(|_, _: &LoadFen, _, cx| {
+                open_fen_window(cx);
+            }))
+            .on_drop(cx.listener(|board, dragged: &DraggedPiece, _, cx| {
+                board.setup_drop(dragged, None);
+                cx.notify();
+            }))
             .bg(rgb(gui::colors::BACKGROUND))
             .size_full()
             .child(
@@ -375,47 +2315,95 @@ impl Render for Board {
                     )))
                     .child(
                         menu_button("Load FEN").on_any_mouse_down(cx.listener(|_, _, _, cx| {
-                            let bounds = Bounds::centered(None, size(px(500.), px(150.)), cx);
-                            let options = WindowOptions {
-                                window_bounds: Some(WindowBounds::Windowed(bounds)),
-                                ..Default::default()
-                            };
-
-                            let text_input = cx.new(|cx| InputField::new(cx));
-                            let input_controller = cx.new(|cx| InputController {
-                                recent_keystrokes: Vec::new(),
-                                focus_handle: cx.focus_handle(),
-                                text_input,
-                            });
-
-                            let window = cx
-                                .open_window(options, |_, cx| {
-                                    cx.new(|cx| FenWindow {
-                                        input_controller,
-                                        focus_handle: cx.focus_handle(),
+                            open_fen_window(cx);
+                        })),
+                    )
+                    .child(menu_button("New Game").on_any_mouse_down(cx.listener(
+                        |board, _, _, cx| {
+                            if !board.is_playing() {
+                                board.new_game = Some(NewGame::default());
+                            }
+                            cx.notify();
+                        },
+                    )))
+                    .child(menu_button("Watch Game").on_any_mouse_down(cx.listener(
+                        |board, _, _, cx| {
+                            if !board.is_playing() {
+                                let engine_count = cx.global::<SharedState>().engines.engines.len();
+                                board.new_match = Some(NewMatch::new(engine_count));
+                            }
+                            cx.notify();
+                        },
+                    )))
+                    .child(menu_button("Setup Position").on_any_mouse_down(cx.listener(
+                        |board, _, _, cx| {
+                            board.enter_setup();
+                            cx.notify();
+                        },
+                    )))
+                    .child(menu_button("Load PGN").on_any_mouse_down(cx.listener(
+                        |_, _, _, cx| {
+                            let task = cx.spawn(async move |this, cx: &mut AsyncApp| {
+                                let file_path = FileDialog::new()
+                                    .add_filter("PGN", &["pgn"])
+                                    .pick_file();
+                                let Some(file_path) = file_path else {
+                                    return;
+                                };
+                                // the first game is shown, the others are listed to pick from
+                                let games = std::fs::read_to_string(&file_path)
+                                    .map_err(|err| err.to_string())
+                                    .and_then(|text| {
+                                        pgn::parse_pgn(&text).map_err(|err| err.to_string())
                                     })
-                                })
-                                .unwrap();
-
-                            let view = window.update(cx, |_, _, cx| cx.entity()).unwrap();
-                            cx.observe_keystrokes(move |_, ev, _, cx| {
-                                view.update(cx, |view, cx| {
-                                    view.input_controller
-                                        .as_mut(cx)
-                                        .recent_keystrokes
-                                        .push(ev.keystroke.clone());
+                                    .and_then(|games| {
+                                        if games.is_empty() {
+                                            Err("The file contains no games".to_string())
+                                        } else {
+                                            Ok(games)
+                                        }
+                                    });
+                                let _ = this.update(cx, |board, cx| {
+                                    match games {
+                                        Ok(games) => {
+                                            board.pgn_games = games;
+                                            board.load_pgn_file_game(0);
+                                            if board.pgn_games.len() == 1 {
+                                                board.pgn_games = Vec::new();
+                                            }
+                                        }
+                                        Err(err) => {
+                                            board.error_message =
+                                                Some(format!("Failed to load PGN: {}", err))
+                                        }
+                                    }
                                     cx.notify();
-                                })
-                            })
-                            .detach();
-                            cx.on_keyboard_layout_change({
-                                move |cx| {
-                                    window.update(cx, |_, _, cx| cx.notify()).ok();
+                                });
+                            });
+                            task.detach();
+                        },
+                    )))
+                    .child(menu_button("Save PGN").on_any_mouse_down(cx.listener(
+                        |board, _, _, cx| {
+                            let pgn = board.to_pgn();
+                            let task = cx.spawn(async move |this, cx: &mut AsyncApp| {
+                                let file_path = FileDialog::new()
+                                    .add_filter("PGN", &["pgn"])
+                                    .set_file_name("game.pgn")
+                                    .save_file();
+                                if let Some(file_path) = file_path {
+                                    if let Err(err) = std::fs::write(&file_path, pgn) {
+                                        let _ = this.update(cx, |board, cx| {
+                                            board.error_message =
+                                                Some(format!("Failed to save PGN: {}", err));
+                                            cx.notify();
+                                        });
+                                    }
                                 }
-                            })
-                            .detach();
-                        })),
-                    )
+                            });
+                            task.detach();
+                        },
+                    )))
                     .child(
                         menu_button("Engines")
                             .on_mouse_down(
@@ -453,10 +2441,8 @@ impl Render for Board {
                                                 };
 
                                                 let window = cx
-                                                    .open_window(options, |_, cx| {
-                                                        cx.new(|_| EngineOptionsWindow {
-                                                            engine_index: index,
-                                                        })
+                                                    .open_window(options, |window, cx| {
+                                                        cx.new(|cx| EngineOptionsWindow::new(index, window, cx))
                                                     })
                                                     .unwrap();
                                                 window.update(cx, |_, _, cx| cx.entity()).unwrap();
@@ -490,7 +2476,7 @@ impl Render for Board {
                                                             .on_mouse_down(
                                                                 MouseButton::Left,
                                                                 cx.listener(|_, _, _, cx| {
-                                                                    let task = cx.spawn(async move |_, cx: &mut AsyncApp| {
+                                                                    let task = cx.spawn(async move |this, cx: &mut AsyncApp| {
                                                                         let file_path = FileDialog::new()
                                                                             .add_filter(
                                                                                 "Engines",
@@ -498,13 +2484,31 @@ impl Render for Board {
                                                                             )
                                                                             .pick_file();
                                                                         if let Some(file_path) = file_path {
-                                                                            let _ = cx.update(move |cx| {
-                                                                                let new_engine = Engine::new(
-                                                                                    file_path.to_str().unwrap(),
-                                                                                    file_path.file_name().unwrap().to_str().unwrap(),
-                                                                                );
-                                                                                cx.global_mut::<SharedState>().engines.engines.push(new_engine);
-                                                                            });
+                                                                            // the UCI handshake waits for the engine, keep it off the UI thread
+                                                                            let new_engine = cx
+                                                                                .background_spawn(async move {
+                                                                                    Engine::new(
+                                                                                        &file_path.to_string_lossy(),
+                                                                                        &file_path
+                                                                                            .file_name()
+                                                                                            .map(|name| name.to_string_lossy().to_string())
+                                                                                            .unwrap_or_default(),
+                                                                                    )
+                                                                                })
+                                                                                .await;
+                                                                            match new_engine {
+                                                                                Ok(new_engine) => {
+                                                                                    let _ = cx.update(move |cx| {
+                                                                                        cx.global_mut::<SharedState>().engines.add_engine(new_engine);
+                                                                                    });
+                                                                                }
+                                                                                Err(err) => {
+                                                                                    let _ = this.update(cx, |board, cx| {
+                                                                                        board.error_message = Some(err.to_string());
+                                                                                        cx.notify();
+                                                                                    });
+                                                                                }
+                                                                            }
                                                                         }
                                                                     });
                                                                     task.detach();
@@ -526,6 +2530,24 @@ impl Render for Board {
                             }),
                     ),
             ) //
+            .when_some(self.error_message.clone(), |this, error| {
+                this.child(
+                    div()
+                        .id("error_message")
+                        .w_full()
+                        .px_2()
+                        .py_0p5()
+                        .text_xs()
+                        .bg(rgb(gui::colors::ERROR))
+                        .text_color(gpui::white())
+                        .cursor_pointer()
+                        .child(format!("{} (click to dismiss)", error))
+                        .on_any_mouse_down(cx.listener(|board, _, _, cx| {
+                            board.error_message = None;
+                            cx.notify();
+                        })),
+                )
+            })
             .child(
                 div()
                     .size_full()
@@ -535,20 +2557,92 @@ impl Render for Board {
                     .flex_col()
                     .gap_2()
                     .child(
-                        div().flex().child(
+                        div().flex().gap_2().child(eval_bar).child(
                                 div()
+                                    .id("board_grid")
                                     .w(board_size)
                                     .h(board_size)
                                     .grid()
                                     .grid_cols(8)
                                     .grid_rows(8)
                                     .gap(px(-1.))
+                                    .relative()
                                     .children(squares)
+                                    .when_some(animation_overlay, |this, overlay| this.child(overlay))
+                                    .child(
+                                        arrows_overlay(arrows, circles, self.is_board_flipped)
+                                            .absolute()
+                                            .top_0()
+                                            .left_0()
+                                            .size_full(),
+                                    )
                                     .on_mouse_down_out(cx.listener(|board, _, _, cx| {
                                         board.selected_square = None;
                                         cx.notify();
+                                    }))
+                                    // a right-drag let go off the board draws nothing
+                                    .on_mouse_up_out(
+                                        MouseButton::Right,
+                                        cx.listener(|board, _, _, _| {
+                                            board.arrow_start = None;
+                                        }),
+                                    )
+                                    .on_hover(cx.listener(|board, hovered: &bool, _, _| {
+                                        if !*hovered {
+                                            board.arrow_start = None;
+                                        }
                                     })),
-                        ),
+                        )
+                        .when_some(setup_controls, |this, panel| this.child(panel))
+                        .when_some(new_game_controls, |this, panel| this.child(panel))
+                        .when_some(new_match_controls, |this, panel| this.child(panel))
+                        .when(!is_setup && !is_new_game, |this| this.child(
+                            div()
+                                .w(px(180.))
+                                .h(board_size)
+                                .flex()
+                                .flex_col()
+                                .gap_1()
+                                .when_some(game_controls, |this, panel| this.child(panel))
+                                .when_some(pgn_games_controls, |this, panel| this.child(panel))
+                                .child(
+                                    div()
+                                        .id("move_list")
+                                        .track_scroll(&self.move_list_scroll)
+                                        .overflow_y_scroll()
+                                        .flex_1()
+                                        .min_h_0()
+                                        .flex()
+                                        .flex_col()
+                                        .py_1()
+                                        .px_2()
+                                        .rounded_sm()
+                                        .bg(rgb(gui::colors::SECONDARY_BACKGROUND))
+                                        .text_sm()
+                                        .text_color(rgb(gui::colors::TEXT))
+                                        .children(move_rows),
+                                )
+                                .when(has_move, |this| {
+                                    this.child(
+                                        div()
+                                            .flex()
+                                            .gap_1()
+                                            .text_xs()
+                                            .child(button("Promote").on_any_mouse_down(cx.listener(
+                                                |board, _, _, cx| {
+                                                    board.promote_variation();
+                                                    cx.notify();
+                                                },
+                                            )))
+                                            .child(button("Delete").on_any_mouse_down(cx.listener(
+                                                |board, _, _, cx| {
+                                                    board.delete_variation();
+                                                    cx.notify();
+                                                },
+                                            ))),
+                                    )
+                                }),
+                        )),
                     ) //
                     .child(
                         div()
@@ -558,6 +2652,9 @@ impl Render for Board {
                                 logo_button("svg/brain.svg", 0.).on_any_mouse_down(cx.listener(
                                     move |board, _event, _window, cx| {
                                         // board.analyze(cx);
+                                        if board.is_playing() {
+                                            return;
+                                        }
                                         cx.global_mut::<SharedState>()
                                             .engines
                                             .toggle_analyze(&board.board);
@@ -578,7 +2675,22 @@ impl Render for Board {
                                 cx.listener(move |board, _event, _window, _cx| {
                                     board.flip_board_visually();
                                 }),
-                            )),
+                            ))
+                            .child(
+                                div()
+                                    .flex()
+                                    .items_center()
+                                    .gap_1()
+                                    .text_xs()
+                                    .text_color(rgb(gui::colors::TEXT))
+                                    .child(check_box(self.show_second_arrow).on_any_mouse_down(
+                                        cx.listener(|board, _, _, cx| {
+                                            board.show_second_arrow = !board.show_second_arrow;
+                                            cx.notify();
+                                        }),
+                                    ))
+                                    .child("2nd line arrows"),
+                            ),
                     ) //
                     .child(
                         div()
@@ -602,22 +2714,9 @@ fn main() {
     Application::new().run(|cx: &mut App| {
         let bounds = Bounds::centered(None, size(px(600.), px(600.0)), cx);
 
-        let engines = vec![
-            Engine::new(
-                "C:/Program Files/stockfish/stockfish-windows-x86-64-avx2.exe",
-                "Stockfish",
-            ),
-            Engine::new(
-                "C:\\Learn\\LearnRust\\chess\\target\\release\\uci.exe",
-                "Queenfish 2",
-            ),
-        ];
         cx.set_global(SharedState {
             fen_string: None,
-            engines: EnginesServices {
-                engines,
-                is_analyzing: false,
-            },
+            engines: EnginesServices::load(),
         });
 
         cx.bind_keys([
@@ -635,6 +2734,7 @@ fn main() {
             KeyBinding::new("end", End, None),
             KeyBinding::new("ctrl-cmd-space", ShowCharacterPalette, None),
         ]);
+        cx.bind_keys(keymap::board_bindings());
 
         cx.open_window(
             WindowOptions {
@@ -645,7 +2745,21 @@ fn main() {
                 }),
                 ..Default::default()
             },
-            |_, cx| cx.new(|cx| Board::new(cx.focus_handle())),
+            |window, cx| {
+                let board = cx.new(|cx| {
+                    let mut board = Board::new(cx.focus_handle());
+                    board.error_message = cx
+                        .global::<SharedState>()
+                        .engines
+                        .registry_error
+                        .as_ref()
+                        .map(|err| format!("{}. Engine changes are not saved until it is fixed.", err));
+                    board.load_registry_engines(cx);
+                    board
+                });
+                window.focus(&board.focus_handle(cx));
+                board
+            },
         )
         .unwrap();
         cx.activate(true);
@@ -2,93 +2,246 @@ use std::io::{BufRead, BufReader, Write};
 use std::path::Path;
 use std::process::{Child, Command, Stdio};
 use std::sync::mpsc;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
 use std::thread;
+use std::time::{Duration, Instant};
+use std::fmt;
 
 #[derive(Debug)]
+pub enum EngineError {
+    NotFound(String),
+    NotExecutable(String),
+    SpawnFailed(std::io::Error),
+    HandshakeTimeout,
+    NotUci,
+    Io(std::io::Error),
+    /// the engine did not answer within the allowed time
+    Timeout,
+    /// the engine process exited or was never started
+    Disconnected,
+}
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::NotFound(path) => write!(f, "Engine path does not exist: {}", path),
+            EngineError::NotExecutable(path) => {
+                write!(f, "Engine file is not an executable: {}", path)
+            }
+            EngineError::SpawnFailed(err) => write!(f, "Failed to start engine process: {}", err),
+            EngineError::HandshakeTimeout => write!(f, "Engine did not answer the UCI handshake"),
+            EngineError::NotUci => write!(f, "Engine is not UCI compatible"),
+            EngineError::Io(err) => write!(f, "Engine I/O error: {}", err),
+            EngineError::Timeout => write!(f, "Engine did not answer in time"),
+            EngineError::Disconnected => write!(f, "Engine process is not running"),
+        }
+    }
+}
+impl std::error::Error for EngineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EngineError::SpawnFailed(err) | EngineError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EngineTimeouts {
+    /// how long to wait for "uciok" after "uci"
+    pub uci: Duration,
+    /// how long to wait for "readyok" after "isready"
+    pub is_ready: Duration,
+    /// extra time allowed on top of the move budget before "bestmove" counts as late
+    pub go: Duration,
+    /// how long a search limited by depth or nodes instead of time may take
+    pub search: Duration,
+}
+impl Default for EngineTimeouts {
+    fn default() -> Self {
+        EngineTimeouts {
+            uci: Duration::from_secs(5),
+            is_ready: Duration::from_secs(5),
+            go: Duration::from_millis(1000),
+            search: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Score {
     Cp(i32),
     Mate(i32),
 }
 
-#[derive(Debug)]
-pub enum AnalysisLine {
-    Move(String),
-    Depth {
-        depth: Option<String>,
-        selective_depth: Option<String>,
-        score: Option<Score>,
-        best_move: Option<String>,
-        nodes: Option<String>,
-        time: Option<String>,
-    },
+impl Score {
+    /// Engines score from the side to move, this turns it to White's point of view
+    pub fn for_white(self, white_to_move: bool) -> Score {
+        if white_to_move {
+            return self;
+        }
+        match self {
+            Score::Cp(cp) => Score::Cp(-cp),
+            Score::Mate(moves) => Score::Mate(-moves),
+        }
+    } //
+
+    /// White's share of an eval bar, between 0 and 1, for a score from the side to move.
+    /// Centipawns go through a logistic curve, a mate fills the bar for the mating side.
+    pub fn white_share(self, white_to_move: bool) -> f32 {
+        let share = match self {
+            Score::Cp(cp) => 1.0 / (1.0 + (-0.00368208 * cp as f32).exp()),
+            Score::Mate(moves) if moves > 0 => 1.0,
+            // "mate 0" and negative mates: the side to move is getting mated
+            Score::Mate(_) => 0.0,
+        };
+        if white_to_move { share } else { 1.0 - share }
+    } //
 }
-impl AnalysisLine {
-    fn new(line: String) -> Option<AnalysisLine> {
-        let line = line.trim().replace("\n", "");
-        let args = line.split_whitespace().collect::<Vec<_>>();
-        if line.starts_with("bestmove") {
-            return Some(AnalysisLine::Move(args[1].to_string()));
-        } else if line.starts_with("info") {
-            let mut depth = None;
-            let mut nodes = None;
-            let mut best_move = None;
-            let mut time = None;
-            let mut score: Option<Score> = None;
-
-            let depth_index = args.iter().position(|str| str == &"depth");
-            if let Some(depth_index) = depth_index {
-                if let Some(depth_str) = args.get(depth_index + 1) {
-                    depth = Some(depth_str.to_string());
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Bound {
+    #[default]
+    Exact,
+    Lower,
+    Upper,
+}
+
+/// Win/draw/loss expectation in permille, from the engine's point of view
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wdl {
+    pub win: u32,
+    pub draw: u32,
+    pub loss: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InfoLine {
+    pub depth: Option<u32>,
+    pub selective_depth: Option<u32>,
+    /// 1-based index of this line when the engine searches several PVs
+    pub multipv: Option<u32>,
+    pub score: Option<Score>,
+    pub bound: Bound,
+    pub wdl: Option<Wdl>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub hashfull: Option<u32>,
+    pub tbhits: Option<u64>,
+    /// search time in ms
+    pub time: Option<u64>,
+    pub current_move: Option<String>,
+    pub current_move_number: Option<u32>,
+    /// principal variation in UCI notation
+    pub pv: Vec<String>,
+    pub string: Option<String>,
+}
+
+const INFO_KEYWORDS: [&str; 20] = [
+    "depth",
+    "seldepth",
+    "time",
+    "nodes",
+    "pv",
+    "multipv",
+    "score",
+    "currmove",
+    "currmovenumber",
+    "hashfull",
+    "nps",
+    "tbhits",
+    "sbhits",
+    "cpuload",
+    "string",
+    "refutation",
+    "currline",
+    "wdl",
+    "lowerbound",
+    "upperbound",
+];
+
+impl InfoLine {
+    pub fn parse(line: &str) -> Option<InfoLine> {
+        let mut args = line.split_whitespace().peekable();
+        if args.next()? != "info" {
+            return None;
+        }
+
+        let mut info = InfoLine::default();
+        while let Some(arg) = args.next() {
+            match arg {
+                "depth" => info.depth = args.next().and_then(|x| x.parse().ok()),
+                "seldepth" => info.selective_depth = args.next().and_then(|x| x.parse().ok()),
+                "multipv" => info.multipv = args.next().and_then(|x| x.parse().ok()),
+                "nodes" => info.nodes = args.next().and_then(|x| x.parse().ok()),
+                "nps" => info.nps = args.next().and_then(|x| x.parse().ok()),
+                "hashfull" => info.hashfull = args.next().and_then(|x| x.parse().ok()),
+                "tbhits" => info.tbhits = args.next().and_then(|x| x.parse().ok()),
+                "time" => info.time = args.next().and_then(|x| x.parse().ok()),
+                "currmove" => info.current_move = args.next().map(|x| x.to_string()),
+                "currmovenumber" => {
+                    info.current_move_number = args.next().and_then(|x| x.parse().ok())
                 }
-            }
-            let score_index = args.iter().position(|str| str == &"cp" || str == &"mate");
-            if let Some(score_index) = score_index {
-                if let Some(score_str) = args.get(score_index + 1) {
-                    if args[score_index] == "cp" {
-                        if let Ok(score_value) = score_str.parse::<i32>() {
-                            score = Some(Score::Cp(score_value));
-                        }
-                    } else if args[score_index] == "mate" {
-                        if let Ok(score_value) = score_str.parse::<i32>() {
-                            score = Some(Score::Mate(score_value));
-                        }
+                "score" => {
+                    let kind = args.next();
+                    let value = args.next().and_then(|x| x.parse::<i32>().ok());
+                    info.score = match (kind, value) {
+                        (Some("cp"), Some(value)) => Some(Score::Cp(value)),
+                        (Some("mate"), Some(value)) => Some(Score::Mate(value)),
+                        _ => None,
+                    };
+                }
+                "lowerbound" => info.bound = Bound::Lower,
+                "upperbound" => info.bound = Bound::Upper,
+                "wdl" => {
+                    let mut next = || args.next().and_then(|x| x.parse::<u32>().ok());
+                    if let (Some(win), Some(draw), Some(loss)) = (next(), next(), next()) {
+                        info.wdl = Some(Wdl { win, draw, loss });
                     }
                 }
-            }
-            let nodes_index = args.iter().position(|str| str == &"nodes");
-            if let Some(nodes_index) = nodes_index {
-                if let Some(nodes_str) = args.get(nodes_index + 1) {
-                    nodes = Some(nodes_str.to_string());
+                "pv" => {
+                    while let Some(mv) = args.next_if(|x| !INFO_KEYWORDS.contains(x)) {
+                        info.pv.push(mv.to_string());
+                    }
                 }
-            }
-            let best_move_index = args.iter().position(|str| str == &"pv");
-            if let Some(best_move_index) = best_move_index {
-                if let Some(best_move_str) = args.get(best_move_index + 1) {
-                    best_move = Some(best_move_str.to_string());
+                "string" => {
+                    info.string = Some(args.by_ref().collect::<Vec<_>>().join(" "));
                 }
-            }
-            let time_index = args.iter().position(|str| str == &"time");
-            if let Some(time_index) = time_index {
-                if let Some(time_str) = args.get(time_index + 1) {
-                    time = Some(time_str.to_string());
+                // moves of lines we do not track, skipped up to the next keyword
+                "refutation" | "currline" => {
+                    while args.next_if(|x| !INFO_KEYWORDS.contains(x)).is_some() {}
                 }
+                _ => {}
             }
+        }
+        Some(info)
+    } //
+
+    pub fn best_move(&self) -> Option<&String> {
+        self.pv.first()
+    } //
+}
 
-            return Some(AnalysisLine::Depth {
-                depth: depth,
-                selective_depth: None,
-                score,
-                best_move: best_move,
-                nodes,
-                time,
-            });
+#[derive(Debug, Clone)]
+pub enum AnalysisLine {
+    Move(String),
+    Info(InfoLine),
+}
+impl AnalysisLine {
+    pub(crate) fn new(line: String) -> Option<AnalysisLine> {
+        let line = line.trim();
+        if line.starts_with("bestmove") {
+            return line
+                .split_whitespace()
+                .nth(1)
+                .map(|best_move| AnalysisLine::Move(best_move.to_string()));
+        } else if line.starts_with("info") {
+            return InfoLine::parse(line).map(AnalysisLine::Info);
         }
         None
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum EngineOption {
     CHECK {
         name: String,
@@ -100,6 +253,178 @@ pub enum EngineOption {
         min: Option<i32>,
         max: Option<i32>,
     },
+    COMBO {
+        name: String,
+        value: String,
+        vars: Vec<String>,
+    },
+    STRING {
+        name: String,
+        value: String,
+    },
+    BUTTON {
+        name: String,
+    },
+} //
+
+impl EngineOption {
+    /// Parses an `option name <id> type <t> [default <x>] [min <x>] [max <x>] [var <x>]*` line.
+    /// Names, defaults and vars may span several words, as in "Skill Level".
+    pub fn parse(line: &str) -> Option<EngineOption> {
+        let mut args = line.split_whitespace();
+        if args.next()? != "option" {
+            return None;
+        }
+
+        let mut name: Vec<&str> = Vec::new();
+        let mut option_type = None;
+        let mut default: Option<Vec<&str>> = None;
+        let mut min = None;
+        let mut max = None;
+        let mut vars: Vec<Vec<&str>> = Vec::new();
+
+        let mut current = "";
+        for arg in args {
+            // only "type" can end a name, so names like "Move Overhead" or "UCI_ShowWDL" stay intact
+            let is_keyword = match current {
+                "name" => arg == "type",
+                _ => matches!(arg, "name" | "type" | "default" | "min" | "max" | "var"),
+            };
+            if is_keyword {
+                current = arg;
+                match arg {
+                    "default" => default = Some(Vec::new()),
+                    "var" => vars.push(Vec::new()),
+                    _ => {}
+                }
+                continue;
+            }
+            match current {
+                "name" => name.push(arg),
+                "type" => option_type = Some(arg),
+                "default" => default.get_or_insert_with(Vec::new).push(arg),
+                "min" => min = arg.parse::<i32>().ok(),
+                "max" => max = arg.parse::<i32>().ok(),
+                "var" => {
+                    if let Some(var) = vars.last_mut() {
+                        var.push(arg);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if name.is_empty() {
+            return None;
+        }
+        let name = name.join(" ");
+        let default = default
+            .map(|words| words.join(" "))
+            .filter(|value| value != "<empty>");
+        let vars = vars
+            .into_iter()
+            .map(|words| words.join(" "))
+            .collect::<Vec<_>>();
+
+        match option_type? {
+            "check" => Some(EngineOption::CHECK {
+                name,
+                value: default.and_then(|value| value.parse::<bool>().ok()).unwrap_or(false),
+            }),
+            "spin" => Some(EngineOption::SPIN {
+                name,
+                value: default
+                    .and_then(|value| value.parse::<i32>().ok())
+                    .or(min)
+                    .unwrap_or(0),
+                min,
+                max,
+            }),
+            "combo" => Some(EngineOption::COMBO {
+                name,
+                value: default
+                    .or_else(|| vars.first().cloned())
+                    .unwrap_or_default(),
+                vars,
+            }),
+            "string" => Some(EngineOption::STRING {
+                name,
+                value: default.unwrap_or_default(),
+            }),
+            "button" => Some(EngineOption::BUTTON { name }),
+            _ => None,
+        }
+    } //
+
+    /// The `setoption` command that applies this option's current value
+    pub fn setoption_command(&self) -> String {
+        match self {
+            EngineOption::CHECK { name, value } => format!("setoption name {} value {}\n", name, value),
+            EngineOption::SPIN { name, value, .. } => {
+                format!("setoption name {} value {}\n", name, value)
+            }
+            EngineOption::COMBO { name, value, .. } | EngineOption::STRING { name, value } => {
+                format!("setoption name {} value {}\n", name, value)
+            }
+            EngineOption::BUTTON { name } => format!("setoption name {}\n", name),
+        }
+    } //
+
+    /// The current value as it would appear after `value` in a `setoption` command
+    pub fn value(&self) -> Option<String> {
+        match self {
+            EngineOption::CHECK { value, .. } => Some(value.to_string()),
+            EngineOption::SPIN { value, .. } => Some(value.to_string()),
+            EngineOption::COMBO { value, .. } | EngineOption::STRING { value, .. } => {
+                Some(value.clone())
+            }
+            EngineOption::BUTTON { .. } => None,
+        }
+    } //
+
+    /// A copy of this option holding `value`, or `None` if it does not fit the option type
+    pub fn with_value(&self, value: &str) -> Option<EngineOption> {
+        match self {
+            EngineOption::CHECK { name, .. } => Some(EngineOption::CHECK {
+                name: name.clone(),
+                value: value.parse::<bool>().ok()?,
+            }),
+            EngineOption::SPIN { name, min, max, .. } => {
+                let value = value.trim().parse::<i32>().ok()?;
+                let value = min.map_or(value, |min| value.max(min));
+                let value = max.map_or(value, |max| value.min(max));
+                Some(EngineOption::SPIN {
+                    name: name.clone(),
+                    value,
+                    min: *min,
+                    max: *max,
+                })
+            }
+            EngineOption::COMBO { name, vars, .. } => vars
+                .iter()
+                .find(|var| var.eq_ignore_ascii_case(value))
+                .map(|var| EngineOption::COMBO {
+                    name: name.clone(),
+                    value: var.clone(),
+                    vars: vars.clone(),
+                }),
+            EngineOption::STRING { name, .. } => Some(EngineOption::STRING {
+                name: name.clone(),
+                value: value.to_string(),
+            }),
+            EngineOption::BUTTON { .. } => None,
+        }
+    } //
+
+    pub fn name(&self) -> &str {
+        match self {
+            EngineOption::CHECK { name, .. }
+            | EngineOption::SPIN { name, .. }
+            | EngineOption::COMBO { name, .. }
+            | EngineOption::STRING { name, .. }
+            | EngineOption::BUTTON { name } => name,
+        }
+    } //
 } //
 
 pub struct Engine {
@@ -108,9 +433,15 @@ pub struct Engine {
     pub engine_options: Vec<EngineOption>,
     pub engine_handle: Option<EngineHandle>,
     pub analysis: Vec<AnalysisLine>,
+    /// latest line for each MultiPV index, index 0 being the best line
+    pub lines: Vec<InfoLine>,
     pub is_show: bool,
+    pub timeouts: EngineTimeouts,
+    pub working_directory: Option<String>,
+    pub arguments: Vec<String>,
 } //
 
+/// A clone has no process of its own, `Engine::start` gives it one with the same option values
 impl Clone for Engine {
     fn clone(&self) -> Self {
         Engine {
@@ -119,97 +450,97 @@ impl Clone for Engine {
             engine_options: self.engine_options.clone(),
             engine_handle: None,
             analysis: Vec::new(),
+            lines: Vec::new(),
             is_show: true,
+            timeouts: self.timeouts,
+            working_directory: self.working_directory.clone(),
+            arguments: self.arguments.clone(),
         }
     }
 }
 
 impl Engine {
-    pub fn new(path: &str, name: &str) -> Self {
+    pub fn new(path: &str, name: &str) -> Result<Self, EngineError> {
+        Engine::with_timeouts(path, name, EngineTimeouts::default())
+    } //
+
+    pub fn with_timeouts(
+        path: &str,
+        name: &str,
+        timeouts: EngineTimeouts,
+    ) -> Result<Self, EngineError> {
+        Engine::with_config(path, name, None, Vec::new(), timeouts)
+    } //
+
+    pub fn with_config(
+        path: &str,
+        name: &str,
+        working_directory: Option<String>,
+        arguments: Vec<String>,
+        timeouts: EngineTimeouts,
+    ) -> Result<Self, EngineError> {
         let path = Path::new(path);
 
-        if !path.exists() {
-            panic!("Engine path does not exist");
-        } else if !path.is_file() {
-            panic!("Engine path is not a file");
+        if !path.is_file() {
+            return Err(EngineError::NotFound(path.to_string_lossy().to_string()));
         }
         if let Some(extension) = path.extension() {
             if extension != "exe" && extension != "" {
-                panic!("Engine file is not an executable");
+                return Err(EngineError::NotExecutable(
+                    path.to_string_lossy().to_string(),
+                ));
             }
-        } else {
-            panic!("Engine file has no extension");
         }
-
-        let mut engine_process = Command::new(path)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
-            .expect("Failed to start engine process");
-
-        let mut stdin = engine_process
-            .stdin
-            .take()
-            .expect("Failed to take engine stdin");
-        let mut stdout = BufReader::new(
-            engine_process
-                .stdout
-                .take()
-                .expect("Failed to take engine stdout"),
-        );
-
-        stdin
-            .write_all("uci\n".as_bytes())
-            .expect("Failed to write 'uci' to engine stdin");
-
-        let is_uci_ok;
-        loop {
-            let mut line = String::new();
-            let _ = stdout.read_line(&mut line);
-            if line.starts_with("uciok") {
-                is_uci_ok = true;
-                break;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = path.metadata().map_err(EngineError::Io)?.permissions().mode();
+            if mode & 0o111 == 0 {
+                return Err(EngineError::NotExecutable(
+                    path.to_string_lossy().to_string(),
+                ));
             }
         }
-        if !is_uci_ok {
-            panic!("Engine is not UCI compatible");
-        }
-
-        stdin
-            .write_all("quit\n".as_bytes())
-            .expect("Error stopping connection");
 
         let mut engine = Engine {
-            path: path.to_str().unwrap().to_string(),
+            path: path.to_string_lossy().to_string(),
             name: name.to_string(),
             engine_options: Vec::new(),
             engine_handle: None,
             analysis: Vec::new(),
+            lines: Vec::new(),
             is_show: true,
+            timeouts,
+            working_directory,
+            arguments,
         };
-        engine.engine_options = engine.detect_engine_options();
+        engine.engine_options = engine.detect_engine_options()?;
 
-        engine
+        Ok(engine)
     } //
 
-    pub fn spawn_handle(&mut self) {
+    pub fn spawn_handle(&mut self) -> Result<(), EngineError> {
         let (cmd_tx, cmd_rx): (Sender<String>, Receiver<String>) = mpsc::channel();
         let (evt_tx, evt_rx): (Sender<String>, Receiver<String>) = mpsc::channel();
 
-        let mut child_process = Command::new(&self.path)
+        let mut command = Command::new(&self.path);
+        command
+            .args(&self.arguments)
             .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
-            .expect("Failed to start engine process");
+            .stdout(Stdio::piped());
+        if let Some(working_directory) = &self.working_directory {
+            command.current_dir(working_directory);
+        }
+        let mut child_process = command.spawn().map_err(EngineError::SpawnFailed)?;
         let mut stdin = child_process
             .stdin
             .take()
-            .expect("Failed to take engine stdin");
+            .ok_or(EngineError::Io(std::io::ErrorKind::BrokenPipe.into()))?;
         let stdout = BufReader::new(
             child_process
                 .stdout
                 .take()
-                .expect("Failed to take engine stdout"),
+                .ok_or(EngineError::Io(std::io::ErrorKind::BrokenPipe.into()))?,
         );
 
         // stdin writer task
@@ -244,18 +575,16 @@ impl Engine {
             tx: cmd_tx,
             rx: evt_rx,
         });
+        Ok(())
     } //
 
     pub fn send_command(&mut self, command: &str) {
-        if self.engine_handle.is_none() {
-            self.spawn_handle();
+        if self.engine_handle.is_none() && self.spawn_handle().is_err() {
+            return;
+        }
+        if let Some(handle) = self.engine_handle.as_ref() {
+            handle.tx.send(command.to_string()).ok();
         }
-        self.engine_handle
-            .as_ref()
-            .unwrap()
-            .tx
-            .send(command.to_string())
-            .ok();
     } //
     pub fn read_line(&self) -> Option<String> {
         if let Some(engine) = self.engine_handle.as_ref() {
@@ -271,71 +600,102 @@ impl Engine {
             None
         }
     } //
-
-    pub fn detect_engine_options(&mut self) -> Vec<EngineOption> {
-        if self.engine_handle.is_none() {
-            self.spawn_handle();
-            // return vec![];
+    pub fn read_line_timeout(&self, timeout: Duration) -> Result<String, EngineError> {
+        match self.engine_handle.as_ref() {
+            Some(engine) => engine.read_line_timeout(timeout),
+            None => Err(EngineError::Disconnected),
         }
-        self.send_command("uci\n");
-        let mut options = vec![];
+    } //
+    pub fn wait_for(&self, token: &str) -> Option<String> {
         loop {
-            if let Some(str) = self.read_line() {
-                if str.starts_with("option") {
-                    let args = str.split_whitespace().collect::<Vec<_>>();
-                    let option_type;
-                    let value;
-                    let name;
-
-                    if let Some(name_index) = args.iter().position(|w| w == &"name") {
-                        name = args[name_index + 1].to_string();
-                    } else {
-                        continue;
-                    }
-                    if let Some(default_index) = args.iter().position(|w| w == &"default") {
-                        value = args[default_index + 1].to_string();
-                    } else {
-                        continue;
-                    }
-                    if let Some(option_type_index) = args.iter().position(|w| w == &"type") {
-                        option_type = args[option_type_index + 1].to_string();
-                    } else {
-                        continue;
-                    }
+            let line = self.read_line()?;
+            if line.trim_start().starts_with(token) {
+                return Some(line.trim().to_string());
+            }
+        }
+    } //
+    pub fn wait_for_timeout(&self, token: &str, timeout: Duration) -> Result<String, EngineError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let line =
+                self.read_line_timeout(deadline.saturating_duration_since(Instant::now()))?;
+            if line.trim_start().starts_with(token) {
+                return Ok(line.trim().to_string());
+            }
+        }
+    } //
 
-                    match option_type.as_str() {
-                        "check" => {
-                            options.push(EngineOption::CHECK {
-                                name,
-                                value: value.parse::<bool>().unwrap(),
-                            });
-                        }
-                        "spin" => {
-                            let mut min = None;
-                            let mut max = None;
-                            if let Some(min_index) = args.iter().position(|w| w == &"min") {
-                                min = Some(args[min_index + 1].parse::<i32>().unwrap());
-                            }
-                            if let Some(max_index) = args.iter().position(|w| w == &"max") {
-                                max = Some(args[max_index + 1].parse::<i32>().unwrap());
-                            }
-                            options.push(EngineOption::SPIN {
-                                name,
-                                value: value.parse::<i32>().unwrap(),
-                                min,
-                                max,
-                            });
-                        }
-                        _ => {}
-                    }
-                } else if str.contains("uciok") {
-                    break;
+    /// Waits for "bestmove" and returns the move together with the last info line that
+    /// carried a score. `None` waits without a deadline.
+    pub fn wait_for_best_move(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<(String, Option<InfoLine>), EngineError> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut last_info = None;
+        loop {
+            let line = match deadline {
+                Some(deadline) => {
+                    self.read_line_timeout(deadline.saturating_duration_since(Instant::now()))?
                 }
-            } else {
-                break;
+                None => self.read_line().ok_or(EngineError::Disconnected)?,
+            };
+            match AnalysisLine::new(line) {
+                Some(AnalysisLine::Move(best_move)) => return Ok((best_move, last_info)),
+                Some(AnalysisLine::Info(info)) if info.score.is_some() => last_info = Some(info),
+                _ => {}
+            }
+        }
+    } //
+
+    pub fn detect_engine_options(&mut self) -> Result<Vec<EngineOption>, EngineError> {
+        if self.engine_handle.is_none() {
+            self.spawn_handle()?;
+        }
+        match self.engine_handle.as_mut() {
+            Some(handle) => handle.detect_engine_options(self.timeouts.uci),
+            None => Err(EngineError::Disconnected),
+        }
+    } //
+
+    /// Starts the process of a cloned engine and, once the handshake is done, sends the
+    /// option values it was cloned with. Options left at the engine's default or with an
+    /// empty value are not sent.
+    pub fn start(&mut self) -> Result<(), EngineError> {
+        let configured = std::mem::take(&mut self.engine_options);
+        self.engine_options = self.detect_engine_options()?;
+        for option in configured {
+            let is_default = self.engine_options.contains(&option);
+            let is_empty = option.value().is_none_or(|value| value.is_empty());
+            if !is_default && !is_empty {
+                self.set_option(option);
             }
         }
-        options
+        Ok(())
+    } //
+
+    pub fn set_option(&mut self, option: EngineOption) {
+        self.send_command(option.setoption_command().as_str());
+        if let Some(existing) = self
+            .engine_options
+            .iter_mut()
+            .find(|existing| existing.name() == option.name())
+        {
+            *existing = option;
+        }
+    } //
+
+    pub fn is_ready(&mut self) -> Result<(), EngineError> {
+        self.send_command("isready\n");
+        self.wait_for_timeout("readyok", self.timeouts.is_ready)
+            .map(|_| ())
+    } //
+
+    /// True once the process is gone, or was never started
+    pub fn has_exited(&mut self) -> bool {
+        self.engine_handle
+            .as_mut()
+            .is_none_or(|handle| !matches!(handle.process.try_wait(), Ok(None)))
     } //
 
     pub fn disconnect(&mut self) {
@@ -349,11 +709,42 @@ impl Engine {
         if let Some(handle) = self.engine_handle.as_mut() {
             while let Some(line) = handle.try_read_line() {
                 if let Some(analysis) = AnalysisLine::new(line) {
+                    if let AnalysisLine::Info(info) = &analysis {
+                        // lines without a PV are progress reports (currmove, hashfull, ...)
+                        if !info.pv.is_empty() {
+                            let index = info.multipv.unwrap_or(1).max(1) as usize - 1;
+                            if self.lines.len() <= index {
+                                self.lines.resize(index + 1, InfoLine::default());
+                            }
+                            self.lines[index] = info.clone();
+                        }
+                    }
                     self.analysis.push(analysis);
                 }
             }
         }
     }
+
+    pub fn multipv_option(&self) -> Option<&EngineOption> {
+        self.engine_options
+            .iter()
+            .find(|option| option.name().eq_ignore_ascii_case("MultiPV"))
+    } //
+    pub fn supports_multipv(&self) -> bool {
+        self.multipv_option().is_some()
+    } //
+    pub fn multipv(&self) -> u32 {
+        match self.multipv_option() {
+            Some(EngineOption::SPIN { value, .. }) => (*value).max(1) as u32,
+            _ => 1,
+        }
+    } //
+    pub fn max_multipv(&self) -> u32 {
+        match self.multipv_option() {
+            Some(EngineOption::SPIN { max, .. }) => max.unwrap_or(i32::MAX).max(1) as u32,
+            _ => 1,
+        }
+    } //
 }
 
 impl Drop for Engine {
@@ -384,67 +775,36 @@ impl EngineHandle {
     pub fn try_read_line(&self) -> Option<String> {
         self.rx.try_recv().ok()
     }
+    pub fn read_line_timeout(&self, timeout: Duration) -> Result<String, EngineError> {
+        self.rx.recv_timeout(timeout).map_err(|err| match err {
+            RecvTimeoutError::Timeout => EngineError::Timeout,
+            RecvTimeoutError::Disconnected => EngineError::Disconnected,
+        })
+    } //
 
-    pub fn detect_engine_options(&mut self) -> Vec<EngineOption> {
+    pub fn detect_engine_options(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Vec<EngineOption>, EngineError> {
         self.send_command("uci\n");
+        let deadline = Instant::now() + timeout;
         let mut options = vec![];
         loop {
-            if let Some(str) = self.read_line() {
-                if str.starts_with("option") {
-                    let args = str.split_whitespace().collect::<Vec<_>>();
-                    let option_type;
-                    let value;
-                    let name;
-
-                    if let Some(name_index) = args.iter().position(|w| w == &"name") {
-                        name = args[name_index + 1].to_string();
-                    } else {
-                        continue;
-                    }
-                    if let Some(default_index) = args.iter().position(|w| w == &"default") {
-                        value = args[default_index + 1].to_string();
-                    } else {
-                        continue;
-                    }
-                    if let Some(option_type_index) = args.iter().position(|w| w == &"type") {
-                        option_type = args[option_type_index + 1].to_string();
-                    } else {
-                        continue;
-                    }
-
-                    match option_type.as_str() {
-                        "check" => {
-                            options.push(EngineOption::CHECK {
-                                name,
-                                value: value.parse::<bool>().unwrap(),
-                            });
-                        }
-                        "spin" => {
-                            let mut min = None;
-                            let mut max = None;
-                            if let Some(min_index) = args.iter().position(|w| w == &"min") {
-                                min = Some(args[min_index + 1].parse::<i32>().unwrap());
-                            }
-                            if let Some(max_index) = args.iter().position(|w| w == &"max") {
-                                max = Some(args[max_index + 1].parse::<i32>().unwrap());
-                            }
-                            options.push(EngineOption::SPIN {
-                                name,
-                                value: value.parse::<i32>().unwrap(),
-                                min,
-                                max,
-                            });
-                        }
-                        _ => {}
-                    }
-                } else if str.contains("uciok") {
-                    break;
+            let str = self
+                .read_line_timeout(deadline.saturating_duration_since(Instant::now()))
+                .map_err(|err| match err {
+                    EngineError::Timeout => EngineError::HandshakeTimeout,
+                    _ => EngineError::NotUci,
+                })?;
+            if str.starts_with("option") {
+                if let Some(option) = EngineOption::parse(&str) {
+                    options.push(option);
                 }
-            } else {
+            } else if str.contains("uciok") {
                 break;
             }
         }
-        options
+        Ok(options)
     } //
 
     pub fn disconnect(&mut self) {
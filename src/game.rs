@@ -1,34 +1,341 @@
-use crate::engine::Engine;
-use queenfish::board::{Board, Turn, Move};
+use crate::engine::{AnalysisLine, Engine, EngineError, EngineTimeouts, InfoLine, Score};
+use crate::notation::pv_to_san;
+use queenfish::board::{Board, DrawReason, Turn};
+use std::fmt;
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TimeControl {
+    /// for analysis, `Game::play` aborts at once since no move would ever come back
     Infinite,
     TimePerMove(i32), // in ms
+    /// base time per side plus an increment after every move, in ms
+    Fischer { base: u64, increment: u64 },
+    /// `base` is added again after every `moves` moves, as in 40 moves in 90 minutes
+    MovesToGo { moves: u32, base: u64, increment: u64 },
+    FixedDepth(u32),
+    FixedNodes(u64),
+    /// the time one side spends is added to the other side's clock, in ms
+    Hourglass { base: u64 },
+}
+
+impl TimeControl {
+    /// Starting time on each clock in ms, `None` when the game is not played on a clock
+    pub fn initial_time(&self) -> Option<u64> {
+        match self {
+            TimeControl::Fischer { base, .. }
+            | TimeControl::MovesToGo { base, .. }
+            | TimeControl::Hourglass { base } => Some(*base),
+            _ => None,
+        }
+    } //
+
+    pub fn increment(&self) -> u64 {
+        match self {
+            TimeControl::Fischer { increment, .. } | TimeControl::MovesToGo { increment, .. } => {
+                *increment
+            }
+            _ => 0,
+        }
+    } //
+
+    /// Value of the PGN `TimeControl` tag, "-" when the game is not played on a clock
+    pub fn pgn_tag(&self) -> String {
+        let seconds = |ms: u64| {
+            if ms % 1000 == 0 {
+                (ms / 1000).to_string()
+            } else {
+                format!("{}", ms as f64 / 1000.0)
+            }
+        };
+        match *self {
+            TimeControl::TimePerMove(time) => format!("1/{}", seconds(time.max(0) as u64)),
+            TimeControl::Fischer { base, increment } if increment == 0 => seconds(base),
+            TimeControl::Fischer { base, increment } => {
+                format!("{}+{}", seconds(base), seconds(increment))
+            }
+            TimeControl::MovesToGo {
+                moves,
+                base,
+                increment,
+            } if increment == 0 => format!("{}/{}", moves, seconds(base)),
+            TimeControl::MovesToGo {
+                moves,
+                base,
+                increment,
+            } => format!("{}/{}+{}", moves, seconds(base), seconds(increment)),
+            TimeControl::Hourglass { base } => format!("*{}", seconds(base)),
+            TimeControl::Infinite | TimeControl::FixedDepth(_) | TimeControl::FixedNodes(_) => {
+                "-".to_string()
+            }
+        }
+    } //
+
+    /// Charges `elapsed` ms to the clock of `side` (0 for white) after it moved and adds
+    /// what the time control gives back. Does nothing when the game is not on a clock.
+    pub fn press_clock(
+        &self,
+        clocks: &mut [i64; 2],
+        moves_made: &mut [u32; 2],
+        side: usize,
+        elapsed: i64,
+    ) {
+        if self.initial_time().is_none() {
+            return;
+        }
+        clocks[side] -= elapsed;
+        moves_made[side] += 1;
+        match *self {
+            TimeControl::Fischer { increment, .. } => clocks[side] += increment as i64,
+            TimeControl::MovesToGo {
+                moves,
+                base,
+                increment,
+            } => {
+                clocks[side] += increment as i64;
+                if moves_made[side] % moves.max(1) == 0 {
+                    clocks[side] += base as i64;
+                }
+            }
+            TimeControl::Hourglass { .. } => clocks[1 - side] += elapsed,
+            _ => {}
+        }
+    } //
+
+    /// How long one search may take before the engine loses, `None` on a clock, which
+    /// decides that itself, and with `Infinite`
+    pub fn search_timeout(&self, timeouts: &EngineTimeouts) -> Option<Duration> {
+        match *self {
+            TimeControl::TimePerMove(time) => {
+                Some(Duration::from_millis(time.max(0) as u64) + timeouts.go)
+            }
+            TimeControl::FixedDepth(_) | TimeControl::FixedNodes(_) => Some(timeouts.search),
+            _ => None,
+        }
+    } //
+
+    /// The `go` command for the side to move. `clocks` holds white's and black's remaining
+    /// time in ms and `moves_made` how many moves the side to move has already played.
+    pub fn go_command(&self, clocks: [i64; 2], moves_made: u32) -> String {
+        let (wtime, btime) = (clocks[0].max(0), clocks[1].max(0));
+        match *self {
+            TimeControl::Infinite => "go infinite\n".to_string(),
+            TimeControl::TimePerMove(time) => format!("go movetime {}\n", time),
+            TimeControl::FixedDepth(depth) => format!("go depth {}\n", depth),
+            TimeControl::FixedNodes(nodes) => format!("go nodes {}\n", nodes),
+            TimeControl::Fischer { increment, .. } => format!(
+                "go wtime {} btime {} winc {} binc {}\n",
+                wtime, btime, increment, increment
+            ),
+            TimeControl::MovesToGo {
+                moves, increment, ..
+            } => {
+                let moves = moves.max(1);
+                format!(
+                    "go wtime {} btime {} winc {} binc {} movestogo {}\n",
+                    wtime,
+                    btime,
+                    increment,
+                    increment,
+                    moves - moves_made % moves
+                )
+            }
+            TimeControl::Hourglass { .. } => format!("go wtime {} btime {}\n", wtime, btime),
+        }
+    } //
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+    TimeForfeit,
+    IllegalMove,
+    EngineCrash,
+    Adjudication,
+    Abort,
+    Resignation,
+    DrawAgreement,
+}
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::ThreefoldRepetition => "threefold repetition",
+            Termination::FiftyMoveRule => "fifty-move rule",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::IllegalMove => "illegal move",
+            Termination::EngineCrash => "engine crash",
+            Termination::Adjudication => "adjudication",
+            Termination::Abort => "abort",
+            Termination::Resignation => "resignation",
+            Termination::DrawAgreement => "mutual agreement",
+        };
+        write!(f, "{}", text)
+    }
+}
+impl Termination {
+    /// Value of the PGN `Termination` tag
+    pub fn pgn_tag(&self) -> &'static str {
+        match self {
+            Termination::Checkmate
+            | Termination::Stalemate
+            | Termination::ThreefoldRepetition
+            | Termination::FiftyMoveRule
+            | Termination::InsufficientMaterial
+            | Termination::Resignation
+            | Termination::DrawAgreement => "normal",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::IllegalMove => "rules infraction",
+            Termination::EngineCrash => "abandoned",
+            Termination::Adjudication => "adjudication",
+            Termination::Abort => "unterminated",
+        }
+    } //
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    WhiteWin,
+    BlackWin,
+    Draw,
+    /// the game was aborted before a result was reached
+    Unfinished,
+}
+impl Outcome {
+    pub fn loss_for(turn: Turn) -> Self {
+        match turn {
+            Turn::WHITE => Outcome::BlackWin,
+            Turn::BLACK => Outcome::WhiteWin,
+        }
+    } //
+
+    /// "1-0", "0-1", "1/2-1/2" or "*"
+    pub fn result_string(self) -> &'static str {
+        match self {
+            Outcome::WhiteWin => "1-0",
+            Outcome::BlackWin => "0-1",
+            Outcome::Draw => "1/2-1/2",
+            Outcome::Unfinished => "*",
+        }
+    } //
+}
+
+#[derive(Debug, Clone)]
+pub struct MoveRecord {
+    pub uci: String,
+    /// think time measured by the arena, in ms
+    pub time: u64,
+    /// time left on the mover's clock after the move, in ms
+    pub clock: Option<i64>,
+    /// last score the engine reported for the move, from its own point of view
+    pub score: Option<Score>,
+    pub depth: Option<u32>,
+}
+
+/// Progress of a watched game, see `Game::watch`
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    /// the side to move was sent "go", `clocks` holding white's and black's time left in ms
+    Turn { white: bool, clocks: [i64; 2] },
+    /// an info line with a PV from the engine to move
+    Info { white: bool, info: InfoLine },
+    Move { record: MoveRecord, clocks: [i64; 2] },
+    Finished(GameResult),
+}
+
+/// Sent to a watched game by whoever watches it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameCommand {
+    /// holds the game after the move being thought about until `Resume`
+    Pause,
+    Resume,
+    /// ends the game at once without a result
+    Abort,
+    Adjudicate(Outcome),
+}
+
+/// What came back while waiting for a watched engine
+enum Reply {
+    BestMove(String, Option<InfoLine>),
+    Stopped(Outcome, Termination),
 }
 
 pub struct Game {
     white: Engine,
     black: Engine,
     moves_list: Vec<String>,
+    moves: Vec<MoveRecord>,
     board: Board,
+    start_fen: Option<String>,
     time_control: TimeControl,
+    /// how far past zero a clock may run before the engine loses on time, in ms
+    time_margin: u64,
+    events: Option<Sender<GameEvent>>,
+    commands: Option<Receiver<GameCommand>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct GameResult {
     white: String,
     black: String,
+    start_fen: Option<String>,
+    time_control: TimeControl,
     moves_list: Vec<String>,
-    result: i32,
+    moves: Vec<MoveRecord>,
+    outcome: Outcome,
+    termination: Termination,
 }
 impl GameResult {
-    pub fn winner(&self) -> String {
-        match self.result {
-            1 => self.white.clone(),
-            -1 => self.black.clone(),
-            _ => String::new(),
+    pub fn winner(&self) -> Option<&str> {
+        match self.outcome {
+            Outcome::WhiteWin => Some(&self.white),
+            Outcome::BlackWin => Some(&self.black),
+            _ => None,
+        }
+    }
+    pub fn white(&self) -> &str {
+        &self.white
+    }
+    pub fn black(&self) -> &str {
+        &self.black
+    }
+    /// `None` for the standard starting position
+    pub fn start_fen(&self) -> Option<&str> {
+        self.start_fen.as_deref()
+    }
+    pub fn time_control(&self) -> TimeControl {
+        self.time_control
+    }
+    pub fn moves_list(&self) -> &Vec<String> {
+        &self.moves_list
+    }
+    /// `moves_list` in SAN, replayed from the start position
+    pub fn san_moves_list(&self) -> Vec<String> {
+        let mut board = Board::new();
+        if let Some(fen) = &self.start_fen {
+            board.load_from_fen(fen);
         }
+        pv_to_san(&mut board, &self.moves_list)
+    }
+    pub fn moves(&self) -> &Vec<MoveRecord> {
+        &self.moves
+    }
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+    pub fn termination(&self) -> Termination {
+        self.termination
+    }
+    /// "1-0", "0-1", "1/2-1/2" or "*"
+    pub fn result_string(&self) -> &'static str {
+        self.outcome.result_string()
     }
 }
 
@@ -38,74 +345,299 @@ impl Game {
             white,
             black,
             moves_list: Vec::new(),
+            moves: Vec::new(),
             board: Board::new(),
+            start_fen: None,
             time_control,
+            time_margin: 50,
+            events: None,
+            commands: None,
         }
     } //
 
-    // pub fn play(&mut self) -> GameResult {
-    //     let start_time = std::time::Instant::now();
-    //     let mut white_process = self.white.spawn_process();
-    //     let mut black_process = self.black.spawn_process();
-
-    //     loop {
-    //         let valid_moves = self.board.generate_moves();
-    //         if valid_moves.is_empty() {
-    //             match self.board.turn {
-    //                 Turn::WHITE => println!("{} wins as black", self.black.name),
-    //                 Turn::BLACK => println!("{} wins as white", self.white.name),
-    //             }
-    //             let result: i32;
-    //             if self.board.is_king_in_check(self.board.turn) {
-    //                 match self.board.turn {
-    //                     Turn::WHITE => result = -1,
-    //                     Turn::BLACK => result = 1,
-    //                 }
-    //             } else {
-    //                 result = 0;
-    //             }
-    //             return GameResult {
-    //                 white: self.white.name.clone(),
-    //                 black: self.black.name.clone(),
-    //                 moves_list: self.moves_list.clone(),
-    //                 result,
-    //             };
-    //         }
-    //         let engine_process = match self.board.turn {
-    //             Turn::WHITE => &mut white_process,
-    //             Turn::BLACK => &mut black_process,
-    //         };
-    //         if self.moves_list.is_empty() {
-    //             engine_process.send_command(format!("position startpos\n").as_str());
-    //         } else {
-    //             engine_process.send_command(
-    //                 format!("position startpos moves {}\n", self.moves_list.join(" ")).as_str(),
-    //             );
-    //         }
-
-    //         match self.time_control {
-    //             TimeControl::Infinite => {
-    //                 engine_process.send_command("go infinite\n");
-    //             }
-    //             TimeControl::TimePerMove(time) => {
-    //                 engine_process.send_command(format!("go movetime {}\n", time).as_str());
-    //             }
-    //         }
-
-    //         loop {
-    //             if let Some(line) = engine_process.read_line() {
-    //                 if line.starts_with("bestmove") {
-    //                     let best_move = line.split_whitespace().nth(1).unwrap();
-    //                     self.moves_list.push(best_move.to_string());
-    //                     self.board.make_move(Move::from_uci(best_move, &self.board));
-    //                     break;
-    //                 } else {
-    //                     // println!("{}", line);
-    //                 }
-    //             }
-    //         }
-    //     } //
-    //     white_process.disconnect();
-    //     black_process.disconnect();
-    // } //
+    pub fn from_fen(white: Engine, black: Engine, time_control: TimeControl, fen: &str) -> Self {
+        let mut game = Game::new(white, black, time_control);
+        game.board.load_from_fen(fen);
+        game.start_fen = Some(fen.to_string());
+        game
+    } //
+
+    pub fn set_time_margin(&mut self, time_margin: u64) {
+        self.time_margin = time_margin;
+    } //
+
+    /// Reports every turn, info line and move on `events` while the game is played, and
+    /// lets `commands` pause, abort or adjudicate it
+    pub fn watch(&mut self, events: Sender<GameEvent>, commands: Receiver<GameCommand>) {
+        self.events = Some(events);
+        self.commands = Some(commands);
+    } //
+
+    fn send_event(&self, event: GameEvent) {
+        if let Some(events) = &self.events {
+            events.send(event).ok();
+        }
+    } //
+
+    fn position_command(&self) -> String {
+        let position = match &self.start_fen {
+            Some(fen) => format!("position fen {}", fen),
+            None => "position startpos".to_string(),
+        };
+        if self.moves_list.is_empty() {
+            format!("{}\n", position)
+        } else {
+            format!("{} moves {}\n", position, self.moves_list.join(" "))
+        }
+    } //
+
+    pub fn play(&mut self) -> GameResult {
+        let mut result: Option<(Outcome, Termination)> = None;
+        if self.time_control == TimeControl::Infinite {
+            result = Some((Outcome::Unfinished, Termination::Abort));
+        }
+        for (engine, turn) in [(&mut self.white, Turn::WHITE), (&mut self.black, Turn::BLACK)] {
+            if result.is_some() {
+                break;
+            }
+            // clones come without a process, starting one also restores their option values
+            let mut ready = Ok(());
+            if engine.engine_handle.is_none() {
+                ready = engine.start();
+            }
+            if ready.is_ok() {
+                engine.send_command("ucinewgame\n");
+                ready = engine.is_ready();
+            }
+            if let Err(err) = ready {
+                result = Some((Outcome::loss_for(turn), engine_failure(&err)));
+                break;
+            }
+        }
+
+        let initial_time = self.time_control.initial_time().unwrap_or(0) as i64;
+        let mut clocks: [i64; 2] = [initial_time, initial_time];
+        let mut moves_made: [u32; 2] = [0, 0];
+
+        let mut is_paused = false;
+        let (outcome, termination) = loop {
+            if let Some(result) = result {
+                break result;
+            }
+            if let Some(result) = position_outcome(&mut self.board) {
+                break result;
+            }
+            if let Some(result) = take_commands(self.commands.as_ref(), &mut is_paused, true) {
+                break result;
+            }
+
+            // the side to move loses if its engine dies, runs out of time or answers with an illegal move
+            let turn = self.board.turn;
+            let side = match turn {
+                Turn::WHITE => 0,
+                Turn::BLACK => 1,
+            };
+            let position = self.position_command();
+            let engine = match turn {
+                Turn::WHITE => &mut self.white,
+                Turn::BLACK => &mut self.black,
+            };
+
+            engine.send_command(position.as_str());
+            if let Some(events) = &self.events {
+                events
+                    .send(GameEvent::Turn {
+                        white: side == 0,
+                        clocks,
+                    })
+                    .ok();
+            }
+            let started = Instant::now();
+            engine.send_command(
+                self.time_control
+                    .go_command(clocks, moves_made[side])
+                    .as_str(),
+            );
+            let timeout = match self.time_control.initial_time() {
+                Some(_) => Some(Duration::from_millis(
+                    clocks[side].max(0) as u64 + self.time_margin,
+                )),
+                None => self.time_control.search_timeout(&engine.timeouts),
+            };
+            let reply = if self.events.is_some() || self.commands.is_some() {
+                wait_for_watched_move(
+                    engine,
+                    timeout,
+                    side == 0,
+                    self.events.as_ref(),
+                    self.commands.as_ref(),
+                    &mut is_paused,
+                )
+            } else {
+                engine
+                    .wait_for_best_move(timeout)
+                    .map(|(best_move, info)| Reply::BestMove(best_move, info))
+            };
+            // measured here rather than trusting the engine's own "time" reports
+            let elapsed = started.elapsed().as_millis() as i64;
+
+            let (best_move, info) = match reply {
+                Ok(Reply::BestMove(best_move, info)) => (best_move, info),
+                Ok(Reply::Stopped(outcome, termination)) => break (outcome, termination),
+                Err(err) => break (Outcome::loss_for(turn), engine_failure(&err)),
+            };
+
+            if self.time_control.initial_time().is_some() {
+                if elapsed > clocks[side] + self.time_margin as i64 {
+                    break (Outcome::loss_for(turn), Termination::TimeForfeit);
+                }
+                self.time_control.press_clock(&mut clocks, &mut moves_made, side, elapsed);
+            }
+
+            let Some(mv) = self
+                .board
+                .generate_moves()
+                .iter()
+                .find(|mv| mv.to_uci() == best_move)
+                .copied()
+            else {
+                break (Outcome::loss_for(turn), Termination::IllegalMove);
+            };
+
+            let record = MoveRecord {
+                uci: best_move.clone(),
+                time: elapsed.max(0) as u64,
+                clock: self.time_control.initial_time().map(|_| clocks[side]),
+                score: info.as_ref().and_then(|info| info.score),
+                depth: info.as_ref().and_then(|info| info.depth),
+            };
+            self.send_event(GameEvent::Move {
+                record: record.clone(),
+                clocks,
+            });
+            self.moves.push(record);
+            self.moves_list.push(best_move);
+            self.board.make_move(mv);
+        }; //
+
+        self.white.disconnect();
+        self.black.disconnect();
+
+        let result = GameResult {
+            white: self.white.name.clone(),
+            black: self.black.name.clone(),
+            start_fen: self.start_fen.clone(),
+            time_control: self.time_control,
+            moves_list: self.moves_list.clone(),
+            moves: self.moves.clone(),
+            outcome,
+            termination,
+        };
+        self.send_event(GameEvent::Finished(result.clone()));
+        result
+    } //
+} //
+
+/// Handles the commands sent so far. While paused and `may_block` it waits for the next
+/// one, a watcher that went away counts as an abort.
+fn take_commands(
+    commands: Option<&Receiver<GameCommand>>,
+    is_paused: &mut bool,
+    may_block: bool,
+) -> Option<(Outcome, Termination)> {
+    let commands = commands?;
+    loop {
+        let command = if *is_paused && may_block {
+            match commands.recv() {
+                Ok(command) => command,
+                Err(_) => return Some((Outcome::Unfinished, Termination::Abort)),
+            }
+        } else {
+            match commands.try_recv() {
+                Ok(command) => command,
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => {
+                    return Some((Outcome::Unfinished, Termination::Abort));
+                }
+            }
+        };
+        match command {
+            GameCommand::Pause => *is_paused = true,
+            GameCommand::Resume => *is_paused = false,
+            GameCommand::Abort => return Some((Outcome::Unfinished, Termination::Abort)),
+            GameCommand::Adjudicate(outcome) => return Some((outcome, Termination::Adjudication)),
+        }
+    }
+} //
+
+/// `Engine::wait_for_best_move` for a watched game: info lines are passed on and the
+/// search is stopped when the watcher aborts or adjudicates. A pause waits for the move.
+fn wait_for_watched_move(
+    engine: &mut Engine,
+    timeout: Option<Duration>,
+    white: bool,
+    events: Option<&Sender<GameEvent>>,
+    commands: Option<&Receiver<GameCommand>>,
+    is_paused: &mut bool,
+) -> Result<Reply, EngineError> {
+    // short reads so commands are seen while the engine thinks
+    let poll = Duration::from_millis(50);
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut last_info = None;
+    loop {
+        if let Some((outcome, termination)) = take_commands(commands, is_paused, false) {
+            engine.send_command("stop\n");
+            return Ok(Reply::Stopped(outcome, termination));
+        }
+        let wait = match deadline {
+            Some(deadline) => deadline.saturating_duration_since(Instant::now()).min(poll),
+            None => poll,
+        };
+        let line = match engine.read_line_timeout(wait) {
+            Ok(line) => line,
+            Err(EngineError::Timeout) if deadline.is_none_or(|deadline| Instant::now() < deadline) => {
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        match AnalysisLine::new(line) {
+            Some(AnalysisLine::Move(best_move)) => return Ok(Reply::BestMove(best_move, last_info)),
+            Some(AnalysisLine::Info(info)) => {
+                if let (Some(events), false) = (events, info.pv.is_empty()) {
+                    events.send(GameEvent::Info { white, info: info.clone() }).ok();
+                }
+                if info.score.is_some() {
+                    last_info = Some(info);
+                }
+            }
+            None => {}
+        }
+    }
+} //
+
+fn engine_failure(err: &EngineError) -> Termination {
+    match err {
+        EngineError::Timeout | EngineError::HandshakeTimeout => Termination::TimeForfeit,
+        _ => Termination::EngineCrash,
+    }
+} //
+
+/// How the game ends in the position on `board`, `None` while it goes on
+pub fn position_outcome(board: &mut Board) -> Option<(Outcome, Termination)> {
+    match board.game_result() {
+        queenfish::board::GameResult::InProgress => None,
+        queenfish::board::GameResult::WhiteWin => Some((Outcome::WhiteWin, Termination::Checkmate)),
+        queenfish::board::GameResult::BlackWin => Some((Outcome::BlackWin, Termination::Checkmate)),
+        queenfish::board::GameResult::Draw(reason) => Some((Outcome::Draw, draw_termination(reason))),
+    }
+} //
+
+/// The rule queenfish gives for a draw
+fn draw_termination(reason: DrawReason) -> Termination {
+    match reason {
+        DrawReason::Stalemate => Termination::Stalemate,
+        DrawReason::ThreefoldRepetition => Termination::ThreefoldRepetition,
+        DrawReason::FiftyMoveRule => Termination::FiftyMoveRule,
+        DrawReason::InsufficientMaterial => Termination::InsufficientMaterial,
+    }
 } //
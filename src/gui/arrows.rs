@@ -0,0 +1,83 @@
+use gpui::{Bounds, Canvas, Hsla, PathBuilder, Pixels, Point, canvas, point, px};
+
+/// An arrow between two squares, a1 = 0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoardArrow {
+    pub from: usize,
+    pub to: usize,
+    pub color: Hsla,
+    /// shaft width as a fraction of the square size
+    pub width: f32,
+}
+
+fn square_center(bounds: &Bounds<Pixels>, square: usize, is_board_flipped: bool) -> Point<Pixels> {
+    let size = bounds.size.width / 8.;
+    let (column, row) = if is_board_flipped {
+        (7 - square % 8, square / 8)
+    } else {
+        (square % 8, 7 - square / 8)
+    };
+    point(
+        bounds.origin.x + size * (column as f32 + 0.5),
+        bounds.origin.y + size * (row as f32 + 0.5),
+    )
+} //
+
+/// Transparent layer drawn over the board grid with arrows and circled squares. It has no
+/// hitbox, so clicks still reach the squares below.
+pub fn arrows_overlay(
+    arrows: Vec<BoardArrow>,
+    circles: Vec<(usize, Hsla)>,
+    is_board_flipped: bool,
+) -> Canvas<()> {
+    canvas(
+        |_, _, _| {},
+        move |bounds, _, window, _| {
+            let square_size = bounds.size.width / 8.;
+
+            for arrow in arrows {
+                let start = square_center(&bounds, arrow.from, is_board_flipped);
+                let end = square_center(&bounds, arrow.to, is_board_flipped);
+                let (dx, dy) = (f32::from(end.x - start.x), f32::from(end.y - start.y));
+                let length = (dx * dx + dy * dy).sqrt();
+                if length == 0. {
+                    continue;
+                }
+                let (ux, uy) = (dx / length, dy / length);
+                let width = f32::from(square_size) * arrow.width;
+                let head_length = (width * 2.5).min(length);
+                let head_half_width = width * 1.4;
+                // the shaft stops where the head starts so the translucent parts do not overlap
+                let neck = point(end.x - px(ux * head_length), end.y - px(uy * head_length));
+
+                let mut shaft = PathBuilder::stroke(px(width));
+                shaft.move_to(start);
+                shaft.line_to(neck);
+                if let Ok(path) = shaft.build() {
+                    window.paint_path(path, arrow.color);
+                }
+
+                let mut head = PathBuilder::fill();
+                head.move_to(end);
+                head.line_to(point(neck.x - px(uy * head_half_width), neck.y + px(ux * head_half_width)));
+                head.line_to(point(neck.x + px(uy * head_half_width), neck.y - px(ux * head_half_width)));
+                head.close();
+                if let Ok(path) = head.build() {
+                    window.paint_path(path, arrow.color);
+                }
+            }
+
+            for (square, color) in circles {
+                let center = square_center(&bounds, square, is_board_flipped);
+                let radius = square_size * 0.45;
+                let mut circle = PathBuilder::stroke(square_size * 0.08);
+                circle.move_to(point(center.x + radius, center.y));
+                circle.arc_to(point(radius, radius), px(0.), false, true, point(center.x - radius, center.y));
+                circle.arc_to(point(radius, radius), px(0.), false, true, point(center.x + radius, center.y));
+                if let Ok(path) = circle.build() {
+                    window.paint_path(path, color);
+                }
+            }
+        },
+    )
+} //
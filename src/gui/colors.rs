@@ -6,5 +6,10 @@ pub const GOLDEN: u32 = 0xCBA135;
 pub const BOARD_DARK: u32 = 0xb58863;
 pub const BOARD_LIGHT: u32 = 0xf0d9b5;
 pub const SQUARE_SELECTION: u32 = 0xaeb187;
+pub const LAST_MOVE_LIGHT: u32 = 0xcdd26a;
+pub const LAST_MOVE_DARK: u32 = 0xaaa23a;
 pub const SUCCESS: u32 = 0x4a934a;
 pub const ERROR: u32 = 0xd32f2f;
+/// arrow colours for the engines shown, in order
+pub const ENGINE_ARROWS: [u32; 4] = [0x3b82f6, 0xe67e22, 0x9b59b6, 0x1abc9c];
+pub const USER_ARROW: u32 = 0x4a934a;
@@ -2,8 +2,10 @@ use crate::gui::constants::{
     BLACK_BISHOP, BLACK_KING, BLACK_KNIGHT, BLACK_PAWN, BLACK_QUEEN, BLACK_ROOK, WHITE_BISHOP,
     WHITE_KING, WHITE_KNIGHT, WHITE_PAWN, WHITE_QUEEN, WHITE_ROOK,
 };
-use gpui::{Div, ElementId, FontWeight, SharedString, Stateful, div, img, prelude::*, px, rgb, deferred};
-use queenfish::board::pieces::PieceType;
+use gpui::{
+    Context, Div, ElementId, FontWeight, Pixels, SharedString, Stateful, Window, deferred, div,
+    img, prelude::*, px, rgb,
+};
 
 use std::path::Path;
 
@@ -71,10 +73,48 @@ pub fn check_box(state: bool) -> impl IntoElement + InteractiveElement {
         .when(state, |this| this.bg(rgb(0x3b82f6)))
 } //
 
+/// A piece being dragged across the board, drawn under the cursor by gpui while the drag lasts
+#[derive(Clone)]
+pub struct DraggedPiece {
+    /// None when dragged from the setup palette
+    pub from: Option<usize>,
+    pub piece: usize,
+    pub size: Pixels,
+}
+
+impl Render for DraggedPiece {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .size(self.size)
+            .p(px(2.))
+            .child(img(Path::new(piece_image(self.piece))).size_full())
+    }
+}
+
+/// Image path for a piece index, 0-5 white pawn to king and 6-11 black
+pub fn piece_image(piece: usize) -> &'static str {
+    match piece {
+        0 => WHITE_PAWN,
+        1 => WHITE_KNIGHT,
+        2 => WHITE_BISHOP,
+        3 => WHITE_ROOK,
+        4 => WHITE_QUEEN,
+        5 => WHITE_KING,
+        6 => BLACK_PAWN,
+        7 => BLACK_KNIGHT,
+        8 => BLACK_BISHOP,
+        9 => BLACK_ROOK,
+        10 => BLACK_QUEEN,
+        11 => BLACK_KING,
+        _ => "",
+    }
+} //
+
 pub fn board_square(
     i: usize,
     selected_square: Option<usize>,
-    piece: Option<PieceType>,
+    last_move: Option<(usize, usize)>,
+    piece: Option<usize>,
     is_board_flipped: bool,
     is_king_in_check: bool,
     current_turn_king_sq: usize,
@@ -82,39 +122,31 @@ pub fn board_square(
     winning_tag_index: Option<usize>,
     losing_tag_index: Option<usize>,
     draw_tag_index: Option<(usize, usize)>,
-) -> impl IntoElement + InteractiveElement {
+) -> Div {
     let file = i % 8;
     let rank = i / 8;
 
-    let mut color = if (file + rank) % 2 == 0 {
+    let is_light = (file + rank) % 2 == 0;
+    let mut color = if is_light {
         super::colors::BOARD_LIGHT
     } else {
         super::colors::BOARD_DARK
     };
 
+    if last_move.is_some_and(|(from, to)| from == i || to == i) {
+        color = if is_light {
+            super::colors::LAST_MOVE_LIGHT
+        } else {
+            super::colors::LAST_MOVE_DARK
+        };
+    }
+
     if let Some(selected_square) = selected_square {
         if selected_square == i {
             color = super::colors::SQUARE_SELECTION;
         }
     }
-    let mut piece_image = "";
-    if let Some(piece) = piece {
-        piece_image = match piece as usize {
-            0 => WHITE_PAWN,
-            1 => WHITE_KNIGHT,
-            2 => WHITE_BISHOP,
-            3 => WHITE_ROOK,
-            4 => WHITE_QUEEN,
-            5 => WHITE_KING,
-            6 => BLACK_PAWN,
-            7 => BLACK_KNIGHT,
-            8 => BLACK_BISHOP,
-            9 => BLACK_ROOK,
-            10 => BLACK_QUEEN,
-            11 => BLACK_KING,
-            _ => "",
-        };
-    }
+    let piece_image = piece.map_or("", piece_image);
 
     let mut element = div()
         .size_full()
@@ -131,9 +163,9 @@ pub fn board_square(
                 .absolute()
                 .right(px(3.))
                 .bottom_0()
-                .text_color(match color {
-                    super::colors::BOARD_DARK => rgb(super::colors::BOARD_LIGHT),
-                    _ => rgb(super::colors::BOARD_DARK),
+                .text_color(match is_light {
+                    false => rgb(super::colors::BOARD_LIGHT),
+                    true => rgb(super::colors::BOARD_DARK),
                 })
                 .text_size(px(10.))
                 .child(((b'a' + (i as u8 % 8)) as char).to_string()),
@@ -145,9 +177,9 @@ pub fn board_square(
                 .absolute()
                 .left(px(3.))
                 .top_0()
-                .text_color(match color {
-                    super::colors::BOARD_DARK => rgb(super::colors::BOARD_LIGHT),
-                    _ => rgb(super::colors::BOARD_DARK),
+                .text_color(match is_light {
+                    false => rgb(super::colors::BOARD_LIGHT),
+                    true => rgb(super::colors::BOARD_DARK),
                 })
                 .text_size(px(10.))
                 .child(((i / 8) + 1).to_string()),
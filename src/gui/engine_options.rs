@@ -1,18 +1,113 @@
-use gpui::{Render, Window, Context, IntoElement, rgb ,div, prelude::*, FontWeight};
+use gpui::{App, Entity, Render, Window, Context, IntoElement, MouseButton, rgb ,div, px, prelude::*, FontWeight, anchored, deferred};
 use crate::gui::state::SharedState;
+use crate::gui::input::InputField;
 use super::components::{check_box, button};
 use crate::engine::EngineOption;
+use std::collections::HashMap;
 
 
+/// Edits the options of one loaded engine, found again by its path and name as the engine
+/// list changes. The window closes when the engine is removed.
 pub struct EngineOptionsWindow {
-    pub engine_index: usize,
+    pub engine_path: String,
+    pub engine_name: String,
+    pub inputs: HashMap<usize, Entity<InputField>>,
+    pub open_combo: Option<usize>,
 } //
 
+impl EngineOptionsWindow {
+    pub fn new(engine_index: usize, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let engine = &cx.global::<SharedState>().engines.engines[engine_index];
+        let engine_path = engine.path.clone();
+        let engine_name = engine.name.clone();
+        let engine_options = engine.engine_options.clone();
+
+        cx.observe_global_in::<SharedState>(window, |engine_options_window, window, cx| {
+            if engine_options_window.engine_index(cx).is_none() {
+                window.remove_window();
+            }
+        })
+        .detach();
+
+        // spin and string options are edited through a text field holding their current value
+        let inputs = engine_options
+            .iter()
+            .enumerate()
+            .filter_map(|(index, option)| {
+                let content = match option {
+                    EngineOption::SPIN { value, .. } => value.to_string(),
+                    EngineOption::STRING { value, .. } => value.clone(),
+                    _ => return None,
+                };
+                let input = cx.new(|cx| {
+                    let mut input = InputField::new(cx);
+                    input.selected_range = content.len()..content.len();
+                    input.content = content.into();
+                    input
+                });
+                Some((index, input))
+            })
+            .collect();
+
+        EngineOptionsWindow {
+            engine_path,
+            engine_name,
+            inputs,
+            open_combo: None,
+        }
+    } //
+
+    /// Where the engine is in the engine list now, `None` once it was removed
+    fn engine_index(&self, cx: &App) -> Option<usize> {
+        cx.global::<SharedState>()
+            .engines
+            .engines
+            .iter()
+            .position(|engine| engine.path == self.engine_path && engine.name == self.engine_name)
+    } //
+
+    fn commit_input(&mut self, index: usize, cx: &mut Context<Self>) {
+        let Some(input) = self.inputs.get(&index).cloned() else {
+            return;
+        };
+        let Some(engine_index) = self.engine_index(cx) else {
+            return;
+        };
+        let content = input.read(cx).content.to_string();
+        let engines = &mut cx.global_mut::<SharedState>().engines;
+        let Some(current) = engines.engines[engine_index]
+            .engine_options
+            .get(index)
+            .cloned()
+        else {
+            return;
+        };
+
+        // a value that does not parse leaves the option unchanged and resets the field
+        let option = current.with_value(&content).unwrap_or(current);
+        let new_content = option.value().unwrap_or_default();
+        engines.set_engine_option(engine_index, option);
+
+        input.update(cx, |input, cx| {
+            input.selected_range = new_content.len()..new_content.len();
+            input.content = new_content.into();
+            cx.notify();
+        });
+        cx.notify();
+    } //
+}
+
 impl Render for EngineOptionsWindow {
-    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let engine = &mut cx.global_mut::<SharedState>().engines.engines[self.engine_index];
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let Some(engine_index) = self.engine_index(cx) else {
+            window.remove_window();
+            return div().id("engine_options_window");
+        };
+        let engine = &cx.global::<SharedState>().engines.engines[engine_index];
         let engine_options = engine.engine_options.clone();
         let engine_is_show = engine.is_show;
+        let open_combo = self.open_combo;
+        let inputs = self.inputs.clone();
 
         let options = engine_options
             .iter()
@@ -28,24 +123,16 @@ impl Render for EngineOptionsWindow {
                         .child(name.clone())
                         .child(check_box(value).on_any_mouse_down(cx.listener(
                             move |engine_options_window, _, _, cx| {
+                                let Some(engine_index) = engine_options_window.engine_index(cx) else {
+                                    return;
+                                };
                                 let state: &mut SharedState = cx.global_mut::<SharedState>();
-                                let engine =
-                                    &mut state.engines.engines[engine_options_window.engine_index];
-
-                                let (name, new_value) = {
-                                    let option = &mut engine.engine_options[index];
-
-                                    match option {
-                                        EngineOption::CHECK { value, name } => {
-                                            *value = !*value;
-                                            (name.clone(), *value)
-                                        }
-                                        _ => return,
-                                    }
-                                }; // ← option borrow ends here
-                                let _ = engine.send_command(
-                                    format!("setoption name {} value {}\n", name, new_value)
-                                        .as_str(),
+                                state.engines.set_engine_option(
+                                    engine_index,
+                                    EngineOption::CHECK {
+                                        name: name.clone(),
+                                        value: !value,
+                                    },
                                 );
                                 cx.notify();
                             },
@@ -53,16 +140,133 @@ impl Render for EngineOptionsWindow {
                 }
                 EngineOption::SPIN {
                     name,
-                    value,
                     min,
                     max,
-                } => div().child(format!(
-                    "{}: {} ({}/{})",
-                    name,
-                    value,
-                    min.unwrap_or(0),
-                    max.unwrap_or(0)
-                )),
+                    ..
+                } => div()
+                    .flex()
+                    .gap_2()
+                    .items_center()
+                    .child(name.clone())
+                    .when_some(inputs.get(&index).cloned(), |this, input| {
+                        this.child(div().w(px(90.)).child(input))
+                    })
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(rgb(super::colors::MUTED))
+                            .child(format!(
+                                "({}/{})",
+                                min.map_or("-".to_string(), |min| min.to_string()),
+                                max.map_or("-".to_string(), |max| max.to_string())
+                            )),
+                    )
+                    .child(div().text_xs().child(button("Set").on_any_mouse_down(cx.listener(
+                        move |engine_options_window, _, _, cx| {
+                            engine_options_window.commit_input(index, cx);
+                        },
+                    )))),
+                EngineOption::COMBO { name, value, vars } => {
+                    let choices = vars
+                        .iter()
+                        .map(|var| {
+                            let name = name.clone();
+                            let vars = vars.clone();
+                            let var = var.clone();
+                            div()
+                                .child(var.clone())
+                                .py_0p5()
+                                .px_1()
+                                .cursor_pointer()
+                                .hover(|this| this.bg(rgb(super::colors::MUTED)))
+                                .on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(move |engine_options_window, _, _, cx| {
+                                        let Some(engine_index) = engine_options_window.engine_index(cx)
+                                        else {
+                                            return;
+                                        };
+                                        cx.global_mut::<SharedState>().engines.set_engine_option(
+                                            engine_index,
+                                            EngineOption::COMBO {
+                                                name: name.clone(),
+                                                value: var.clone(),
+                                                vars: vars.clone(),
+                                            },
+                                        );
+                                        engine_options_window.open_combo = None;
+                                        cx.notify();
+                                    }),
+                                )
+                        })
+                        .collect::<Vec<_>>();
+
+                    div()
+                        .flex()
+                        .gap_2()
+                        .items_center()
+                        .child(name.clone())
+                        .child(
+                            div()
+                                .px_1()
+                                .border_1()
+                                .border_color(rgb(super::colors::MUTED))
+                                .rounded_sm()
+                                .cursor_pointer()
+                                .child(format!("{} ▾", value))
+                                .on_mouse_down(
+                                    MouseButton::Left,
+                                    cx.listener(move |engine_options_window, _, _, cx| {
+                                        engine_options_window.open_combo = Some(index);
+                                        cx.notify();
+                                    }),
+                                )
+                                .when(open_combo == Some(index), |this| {
+                                    this.child(
+                                        deferred(
+                                            anchored().snap_to_window_with_margin(px(8.)).child(
+                                                div()
+                                                    .children(choices)
+                                                    .text_color(rgb(super::colors::TEXT))
+                                                    .bg(rgb(super::colors::SECONDARY_BACKGROUND))
+                                                    .on_mouse_down_out(cx.listener(
+                                                        |engine_options_window, _, _, cx| {
+                                                            engine_options_window.open_combo = None;
+                                                            cx.notify();
+                                                        },
+                                                    )),
+                                            ),
+                                        )
+                                        .priority(0),
+                                    )
+                                }),
+                        )
+                }
+                EngineOption::STRING { name, .. } => div()
+                    .flex()
+                    .gap_2()
+                    .items_center()
+                    .child(name.clone())
+                    .when_some(inputs.get(&index).cloned(), |this, input| {
+                        this.child(div().w(px(180.)).child(input))
+                    })
+                    .child(div().text_xs().child(button("Set").on_any_mouse_down(cx.listener(
+                        move |engine_options_window, _, _, cx| {
+                            engine_options_window.commit_input(index, cx);
+                        },
+                    )))),
+                EngineOption::BUTTON { name } => {
+                    let name = name.clone();
+                    div().my_1().text_xs().child(button(&name).on_any_mouse_down(cx.listener(
+                        move |engine_options_window, _, _, cx| {
+                            let Some(engine_index) = engine_options_window.engine_index(cx) else {
+                                return;
+                            };
+                            let engine = &mut cx.global_mut::<SharedState>().engines.engines[engine_index];
+                            engine.set_option(EngineOption::BUTTON { name: name.clone() });
+                        },
+                    )))
+                }
             });
         div()
             .id("engine_options_window")
@@ -88,8 +292,10 @@ impl Render for EngineOptionsWindow {
                     .child("Show Analysis")
                     .child(check_box(engine_is_show))
                     .on_any_mouse_down(cx.listener(|engine_options_window, _, _, cx| {
-                        let engine = &mut cx.global_mut::<SharedState>().engines.engines
-                            [engine_options_window.engine_index];
+                        let Some(engine_index) = engine_options_window.engine_index(cx) else {
+                            return;
+                        };
+                        let engine = &mut cx.global_mut::<SharedState>().engines.engines[engine_index];
                         engine.is_show = !engine.is_show;
                         cx.notify();
                     })),
@@ -106,10 +312,9 @@ impl Render for EngineOptionsWindow {
                 button("Remove Engine").on_any_mouse_down(cx.listener(
                     |engine_options_window, _, window, cx| {
                         window.remove_window();
-                        cx.global_mut::<SharedState>()
-                            .engines
-                            .engines
-                            .remove(engine_options_window.engine_index);
+                        if let Some(engine_index) = engine_options_window.engine_index(cx) {
+                            cx.global_mut::<SharedState>().engines.remove_engine(engine_index);
+                        }
                         cx.notify();
                     },
                 )),
@@ -0,0 +1,91 @@
+use gpui::{KeyBinding, actions};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+actions!(
+    board,
+    [
+        PreviousMove,
+        NextMove,
+        FirstMove,
+        LastMove,
+        FlipBoard,
+        ToggleAnalysis,
+        ResetBoard,
+        LoadFen,
+    ]
+);
+
+/// Key context of the board view, board bindings only apply inside it
+pub const BOARD_CONTEXT: &str = "Board";
+
+const DEFAULT_BINDINGS: [(&str, &str); 8] = [
+    ("left", "PreviousMove"),
+    ("right", "NextMove"),
+    ("home", "FirstMove"),
+    ("end", "LastMove"),
+    ("f", "FlipBoard"),
+    ("space", "ToggleAnalysis"),
+    ("ctrl-r", "ResetBoard"),
+    ("ctrl-l", "LoadFen"),
+];
+
+/// `keymap.toml`, e.g.
+///
+/// ```toml
+/// [bindings]
+/// "ctrl-left" = "PreviousMove"
+/// "a" = "ToggleAnalysis"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+struct KeymapFile {
+    #[serde(default)]
+    bindings: BTreeMap<String, String>,
+}
+
+/// `<config dir>/arena/keymap.toml`
+pub fn keymap_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("arena").join("keymap.toml"))
+} //
+
+/// Keystroke -> action name. An action named in `overrides` loses its default keys.
+pub fn merge_bindings(overrides: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut bindings = DEFAULT_BINDINGS
+        .iter()
+        .filter(|(_, action)| !overrides.values().any(|name| name == action))
+        .map(|(key, action)| (key.to_string(), action.to_string()))
+        .collect::<BTreeMap<_, _>>();
+    bindings.extend(overrides.clone());
+    bindings
+} //
+
+fn key_binding(key: &str, action: &str) -> Option<KeyBinding> {
+    let context = Some(BOARD_CONTEXT);
+    Some(match action {
+        "PreviousMove" => KeyBinding::new(key, PreviousMove, context),
+        "NextMove" => KeyBinding::new(key, NextMove, context),
+        "FirstMove" => KeyBinding::new(key, FirstMove, context),
+        "LastMove" => KeyBinding::new(key, LastMove, context),
+        "FlipBoard" => KeyBinding::new(key, FlipBoard, context),
+        "ToggleAnalysis" => KeyBinding::new(key, ToggleAnalysis, context),
+        "ResetBoard" => KeyBinding::new(key, ResetBoard, context),
+        "LoadFen" => KeyBinding::new(key, LoadFen, context),
+        _ => return None,
+    })
+} //
+
+/// The board bindings with the user's keymap applied. A missing or unreadable keymap
+/// leaves the defaults, unknown action names are skipped.
+pub fn board_bindings() -> Vec<KeyBinding> {
+    let overrides = keymap_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| toml::from_str::<KeymapFile>(&content).ok())
+        .unwrap_or_default()
+        .bindings;
+    merge_bindings(&overrides)
+        .iter()
+        .filter_map(|(key, action)| key_binding(key, action))
+        .collect()
+} //
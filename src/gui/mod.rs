@@ -5,4 +5,8 @@ pub mod constants;
 pub mod fen_window;
 pub mod state;
 pub mod components;
-pub mod engine_options;
\ No newline at end of file
+pub mod engine_options;
+pub mod keymap;
+pub mod arrows;
+pub mod settings;
+pub mod play;
@@ -0,0 +1,349 @@
+use crate::engine::{EngineTimeouts, Score};
+use crate::game::{GameCommand, GameEvent, Outcome, Termination, TimeControl};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::time::Instant;
+
+/// Time controls offered when starting a game against an engine
+pub const TIME_CONTROLS: [(&str, TimeControl); 6] = [
+    ("1+0", TimeControl::Fischer { base: 60_000, increment: 0 }),
+    ("3+2", TimeControl::Fischer { base: 180_000, increment: 2_000 }),
+    ("5+3", TimeControl::Fischer { base: 300_000, increment: 3_000 }),
+    ("15+10", TimeControl::Fischer { base: 900_000, increment: 10_000 }),
+    ("1 s/move", TimeControl::TimePerMove(1_000)),
+    ("Depth 10", TimeControl::FixedDepth(10)),
+];
+
+/// Choices made before a game against an engine starts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NewGame {
+    pub user_is_white: bool,
+    /// index into the loaded engines
+    pub engine_index: usize,
+    /// index into `TIME_CONTROLS`
+    pub time_control_index: usize,
+    /// Start was pressed with moves on the board, pressing it again discards them
+    pub is_confirming: bool,
+}
+
+impl Default for NewGame {
+    fn default() -> Self {
+        NewGame {
+            user_is_white: true,
+            engine_index: 0,
+            time_control_index: 1,
+            is_confirming: false,
+        }
+    }
+}
+
+/// A game between the user and one of the loaded engines
+pub struct VsEngine {
+    /// new for every game so an old tick loop can tell it is no longer needed
+    pub id: usize,
+    pub engine_name: String,
+    pub user_is_white: bool,
+    pub time_control: TimeControl,
+    /// white's and black's remaining time in ms, as of `turn_started`
+    clocks: [i64; 2],
+    moves_made: [u32; 2],
+    /// side whose clock is running
+    white_to_move: bool,
+    turn_started: Instant,
+    /// "go" was sent and the engine's bestmove is pending
+    pub is_engine_thinking: bool,
+    /// when the last "go" was sent
+    pub search_started: Instant,
+    /// the engine's view of the position after its last move, used to answer draw offers
+    pub engine_score: Option<Score>,
+    pub draw_declined: bool,
+    pub result: Option<(Outcome, Termination)>,
+}
+
+impl VsEngine {
+    pub fn new(
+        id: usize,
+        engine_name: String,
+        user_is_white: bool,
+        time_control: TimeControl,
+        white_to_move: bool,
+    ) -> Self {
+        let initial_time = time_control.initial_time().unwrap_or(0) as i64;
+        VsEngine {
+            id,
+            engine_name,
+            user_is_white,
+            time_control,
+            clocks: [initial_time, initial_time],
+            moves_made: [0, 0],
+            white_to_move,
+            turn_started: Instant::now(),
+            is_engine_thinking: false,
+            search_started: Instant::now(),
+            engine_score: None,
+            draw_declined: false,
+            result: None,
+        }
+    } //
+
+    pub fn is_over(&self) -> bool {
+        self.result.is_some()
+    } //
+
+    /// Side whose clock is running
+    pub fn white_to_move(&self) -> bool {
+        self.white_to_move
+    } //
+
+    pub fn is_engine_turn(&self) -> bool {
+        self.white_to_move != self.user_is_white && !self.is_over()
+    } //
+
+    /// White and black player names for the PGN tags
+    pub fn players(&self) -> [&str; 2] {
+        if self.user_is_white {
+            ["Player", &self.engine_name]
+        } else {
+            [&self.engine_name, "Player"]
+        }
+    } //
+
+    /// Remaining time in ms with the running clock counted down to now, `None` when the
+    /// game is not played on a clock
+    pub fn clock(&self, white: bool) -> Option<i64> {
+        self.time_control.initial_time()?;
+        let side = if white { 0 } else { 1 };
+        let mut clock = self.clocks[side];
+        if white == self.white_to_move && !self.is_over() {
+            clock -= self.turn_started.elapsed().as_millis() as i64;
+        }
+        Some(clock)
+    } //
+
+    pub fn is_flagged(&self) -> bool {
+        self.clock(self.white_to_move).is_some_and(|clock| clock < 0)
+    } //
+
+    /// Stops the clock of the side that just moved and starts the other one
+    pub fn press_clock(&mut self) {
+        let side = if self.white_to_move { 0 } else { 1 };
+        let elapsed = self.turn_started.elapsed().as_millis() as i64;
+        self.time_control.press_clock(&mut self.clocks, &mut self.moves_made, side, elapsed);
+        self.white_to_move = !self.white_to_move;
+        self.turn_started = Instant::now();
+        self.draw_declined = false;
+    } //
+
+    /// The engine is still searching past the deadline of a time control without a clock
+    pub fn is_search_overdue(&self, timeouts: &EngineTimeouts) -> bool {
+        self.is_engine_thinking
+            && self
+                .time_control
+                .search_timeout(timeouts)
+                .is_some_and(|timeout| self.search_started.elapsed() > timeout)
+    } //
+
+    /// The `go` command for the engine, sent when its clock starts
+    pub fn go_command(&self) -> String {
+        let side = if self.white_to_move { 0 } else { 1 };
+        let clocks = [
+            self.clock(true).unwrap_or(0),
+            self.clock(false).unwrap_or(0),
+        ];
+        self.time_control.go_command(clocks, self.moves_made[side])
+    } //
+
+    /// Engines cannot answer draw offers over UCI, so the offer is taken when the engine
+    /// does not think it is better
+    pub fn engine_accepts_draw(&self) -> bool {
+        match self.engine_score {
+            Some(Score::Cp(cp)) => cp <= 0,
+            Some(Score::Mate(moves)) => moves < 0,
+            None => false,
+        }
+    } //
+}
+
+/// Engines and time control of an engine game to watch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NewMatch {
+    /// indexes into the loaded engines
+    pub white_index: usize,
+    pub black_index: usize,
+    /// index into `TIME_CONTROLS`
+    pub time_control_index: usize,
+    /// Start was pressed with moves on the board, pressing it again discards them
+    pub is_confirming: bool,
+}
+
+impl NewMatch {
+    /// The first two engines against each other, or the only one against itself
+    pub fn new(engine_count: usize) -> Self {
+        NewMatch {
+            white_index: 0,
+            black_index: if engine_count > 1 { 1 } else { 0 },
+            time_control_index: 1,
+            is_confirming: false,
+        }
+    } //
+}
+
+/// Latest line of a watched engine, the PV already in SAN where it could be read
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchedLine {
+    pub score: Option<Score>,
+    pub depth: Option<u32>,
+    pub selective_depth: Option<u32>,
+    pub pv: String,
+}
+
+/// An engine game played on its own thread, see `Game::watch`
+pub struct WatchedGame {
+    /// new for every game so an old tick loop can tell it is no longer needed
+    pub id: usize,
+    /// white and black engine names
+    pub names: [String; 2],
+    events: Receiver<GameEvent>,
+    commands: Sender<GameCommand>,
+    /// white's and black's remaining time in ms, as of `turn_started`
+    clocks: [i64; 2],
+    /// side whose engine is thinking, None between moves
+    thinking: Option<bool>,
+    turn_started: Instant,
+    has_clock: bool,
+    /// white's and black's lines by MultiPV index
+    pub lines: [Vec<WatchedLine>; 2],
+    pub is_paused: bool,
+    pub result: Option<(Outcome, Termination)>,
+}
+
+impl WatchedGame {
+    pub fn new(
+        id: usize,
+        names: [String; 2],
+        time_control: TimeControl,
+        events: Receiver<GameEvent>,
+        commands: Sender<GameCommand>,
+    ) -> Self {
+        let initial_time = time_control.initial_time().unwrap_or(0) as i64;
+        WatchedGame {
+            id,
+            names,
+            events,
+            commands,
+            clocks: [initial_time, initial_time],
+            thinking: None,
+            turn_started: Instant::now(),
+            has_clock: time_control.initial_time().is_some(),
+            lines: [Vec::new(), Vec::new()],
+            is_paused: false,
+            result: None,
+        }
+    } //
+
+    pub fn is_over(&self) -> bool {
+        self.result.is_some()
+    } //
+
+    /// Side whose engine is thinking, None between moves and after the game
+    pub fn thinking(&self) -> Option<bool> {
+        self.thinking
+    } //
+
+    /// Remaining time in ms with the running clock counted down to now, `None` when the
+    /// game is not played on a clock
+    pub fn clock(&self, white: bool) -> Option<i64> {
+        if !self.has_clock {
+            return None;
+        }
+        let side = if white { 0 } else { 1 };
+        let mut clock = self.clocks[side];
+        if self.thinking == Some(white) {
+            clock -= self.turn_started.elapsed().as_millis() as i64;
+        }
+        Some(clock)
+    } //
+
+    /// The next event the game sent. A game thread that is gone without a result counts
+    /// as aborted.
+    pub fn next_event(&mut self) -> Option<GameEvent> {
+        match self.events.try_recv() {
+            Ok(event) => Some(event),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                if self.result.is_none() {
+                    self.result = Some((Outcome::Unfinished, Termination::Abort));
+                    self.thinking = None;
+                }
+                None
+            }
+        }
+    } //
+
+    /// Updates the clocks and the result, info lines are added with `set_line`
+    pub fn apply(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Turn { white, clocks } => {
+                self.clocks = *clocks;
+                self.thinking = Some(*white);
+                self.turn_started = Instant::now();
+                self.lines[if *white { 0 } else { 1 }].clear();
+            }
+            GameEvent::Info { .. } => {}
+            GameEvent::Move { clocks, .. } => {
+                self.clocks = *clocks;
+                self.thinking = None;
+            }
+            GameEvent::Finished(result) => {
+                self.result = Some((result.outcome(), result.termination()));
+                self.thinking = None;
+            }
+        }
+    } //
+
+    /// `index` is the 0-based MultiPV index
+    pub fn set_line(&mut self, white: bool, index: usize, line: WatchedLine) {
+        let lines = &mut self.lines[if white { 0 } else { 1 }];
+        if lines.len() <= index {
+            lines.resize(
+                index + 1,
+                WatchedLine {
+                    score: None,
+                    depth: None,
+                    selective_depth: None,
+                    pv: String::new(),
+                },
+            );
+        }
+        lines[index] = line;
+    } //
+
+    pub fn send(&mut self, command: GameCommand) {
+        match command {
+            GameCommand::Pause => self.is_paused = true,
+            GameCommand::Resume => self.is_paused = false,
+            _ => {}
+        }
+        self.commands.send(command).ok();
+    } //
+}
+
+/// `<data dir>/arena/games.pgn`, where finished games against engines are collected
+pub fn games_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("arena").join("games.pgn"))
+} //
+
+/// Appends a game to `games_path` and returns where it went
+pub fn save_game(pgn: &str) -> std::io::Result<PathBuf> {
+    let path = games_path()
+        .ok_or_else(|| std::io::Error::other("No user data directory is available"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    // every exported game already ends with a blank line
+    file.write_all(pgn.as_bytes())?;
+    Ok(path)
+} //
@@ -0,0 +1,41 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// `settings.toml`, e.g.
+///
+/// ```toml
+/// animation_ms = 250
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct GuiSettings {
+    /// how long a piece takes to slide to its new square, 0 turns animations off
+    pub animation_ms: u64,
+}
+
+impl Default for GuiSettings {
+    fn default() -> Self {
+        GuiSettings { animation_ms: 200 }
+    }
+}
+
+impl GuiSettings {
+    /// `<config dir>/arena/settings.toml`
+    pub fn settings_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("arena").join("settings.toml"))
+    } //
+
+    /// A missing or unreadable file leaves the defaults
+    pub fn load() -> Self {
+        GuiSettings::settings_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default()
+    } //
+
+    pub fn animation_duration(&self) -> Duration {
+        Duration::from_millis(self.animation_ms)
+    } //
+}
@@ -1,10 +1,16 @@
 use gpui::{Global, SharedString};
-use crate::Engine;
+use crate::{Engine, EngineOption};
+use crate::registry::{EngineEntry, EngineRegistry};
 use queenfish::board::Board as QueenFishBoard;
 
 pub struct EnginesServices {
     pub engines: Vec<Engine>,
     pub is_analyzing: bool,
+    /// position the engines' lines are for, as `Board::to_fen` writes it
+    pub analysis_fen: Option<String>,
+    pub registry: EngineRegistry,
+    /// why `engines.toml` could not be read, the file is not saved over while this is set
+    pub registry_error: Option<String>,
 }
 
 impl EnginesServices {
@@ -12,8 +18,60 @@ impl EnginesServices {
         EnginesServices {
             engines: vec![],
             is_analyzing: false,
+            analysis_fen: None,
+            registry: EngineRegistry::default(),
+            registry_error: None,
         }
     }
+    /// Reads the registry file without starting its engines, the GUI starts them in the
+    /// background. Entries that fail to start stay in the registry so a temporarily missing
+    /// binary is not forgotten.
+    pub fn load() -> Self {
+        let (registry, registry_error) = match EngineRegistry::load() {
+            Ok(registry) => (registry, None),
+            Err(err) => (EngineRegistry::default(), Some(err.to_string())),
+        };
+        EnginesServices {
+            engines: Vec::new(),
+            is_analyzing: false,
+            analysis_fen: None,
+            registry,
+            registry_error,
+        }
+    }
+    fn save_registry(&self) {
+        if self.registry_error.is_none() {
+            self.registry.save().ok();
+        }
+    }
+    pub fn add_engine(&mut self, engine: Engine) {
+        if self.registry.entry(&engine.path).is_none() {
+            self.registry.engines.push(EngineEntry::from_engine(&engine));
+            self.save_registry();
+        }
+        self.engines.push(engine);
+    }
+    pub fn remove_engine(&mut self, index: usize) {
+        if index >= self.engines.len() {
+            return;
+        }
+        let engine = self.engines.remove(index);
+        // the same binary loaded twice shares one entry
+        if self.engines.iter().all(|other| other.path != engine.path) {
+            self.registry.engines.retain(|entry| entry.path != engine.path);
+            self.save_registry();
+        }
+    }
+    pub fn set_engine_option(&mut self, index: usize, option: EngineOption) {
+        let Some(engine) = self.engines.get_mut(index) else {
+            return;
+        };
+        if let (Some(entry), Some(value)) = (self.registry.entry_mut(&engine.path), option.value()) {
+            entry.options.insert(option.name().to_string(), value);
+            self.save_registry();
+        }
+        engine.set_option(option);
+    }
     pub fn toggle_analyze(&mut self, board: &QueenFishBoard) {
         if self.is_analyzing {
             self.is_analyzing = false;
@@ -23,12 +81,38 @@ impl EnginesServices {
             return;
         }
         self.is_analyzing = true;
+        let fen = board.to_fen();
         self.engines.iter_mut().for_each(|engine| {
             engine.send_command("stop\n");
             engine.analysis.clear();
-            engine.send_command(format!("position fen {} 0 1\n", board.to_fen()).as_str());
+            engine.lines.clear();
+            engine.send_command(format!("position fen {} 0 1\n", fen).as_str());
             engine.send_command("go\n")
         });
+        self.analysis_fen = Some(fen);
+    }
+    /// MultiPV can only change between searches, so a running analysis is restarted on the
+    /// position the other engines are analysing
+    pub fn set_multipv(&mut self, index: usize, multipv: u32) {
+        let Some(option) = self
+            .engines
+            .get(index)
+            .and_then(|engine| engine.multipv_option())
+            .and_then(|option| option.with_value(&multipv.to_string()))
+        else {
+            return;
+        };
+        if self.is_analyzing {
+            self.engines[index].send_command("stop\n");
+        }
+        self.set_engine_option(index, option);
+
+        let engine = &mut self.engines[index];
+        engine.lines.clear();
+        if let Some(fen) = self.analysis_fen.as_ref().filter(|_| self.is_analyzing) {
+            engine.send_command(format!("position fen {} 0 1\n", fen).as_str());
+            engine.send_command("go\n");
+        }
     }
     pub fn poll_engines(&mut self) {
         self.engines
@@ -1,11 +1,17 @@
 pub mod engine;
 pub mod game;
 pub mod tournament;
+pub mod registry;
+pub mod notation;
+pub mod pgn;
+pub mod tree;
+pub mod setup;
 pub mod gui;
 
 pub use engine::*;
 pub use game::*;
 pub use tournament::*;
+pub use registry::*;
 
 #[cfg(test)]
 mod test {
@@ -14,6 +20,7 @@ mod test {
     use queenfish::board::rook_magic::init_rook_magics;
 
     #[test]
+    #[ignore = "starts the Queenfish and Stockfish binaries from their local Windows paths"]
     fn it_works() {
         init_bishop_magics();
         init_rook_magics();
@@ -21,16 +28,459 @@ mod test {
         let engine = Engine::new(
             "C:\\Learn\\LearnRust\\chess\\target\\release\\uci.exe",
             "Queenfish 2",
-        );
+        )
+        .unwrap();
 
         let engine2 = Engine::new(
             "C:\\Program Files\\stockfish\\stockfish-windows-x86-64-avx2.exe",
             "Stockfish",
-        );
+        )
+        .unwrap();
+
+        let mut game = Game::new(engine.clone(), engine2.clone(), TimeControl::TimePerMove(50));
+        let game_result = game.play();
+        assert!(!matches!(game_result.outcome(), Outcome::Unfinished));
 
         let mut tournament = Tournament::new(5, engine , engine2 , TimeControl::TimePerMove(50));
-        // let tournament_result = tournament.start();
-        // dbg!(tournament_result);
-        // dbg!(game.play());
+        let tournament_result = tournament.start();
+        assert_eq!(tournament_result.total_games(), 5);
+        assert_eq!(
+            tournament_result.engine1_won()
+                + tournament_result.engine2_won()
+                + tournament_result.draws()
+                + tournament_result.unfinished(),
+            5
+        );
+    }
+
+    #[test]
+    fn parses_uci_options() {
+        assert_eq!(
+            EngineOption::parse("option name Skill Level type spin default 20 min 0 max 20"),
+            Some(EngineOption::SPIN {
+                name: "Skill Level".to_string(),
+                value: 20,
+                min: Some(0),
+                max: Some(20),
+            })
+        );
+        assert_eq!(
+            EngineOption::parse("option name SyzygyPath type string default <empty>"),
+            Some(EngineOption::STRING {
+                name: "SyzygyPath".to_string(),
+                value: String::new(),
+            })
+        );
+        assert_eq!(
+            EngineOption::parse(
+                "option name Style type combo default Normal var Solid var Normal var Risky Play"
+            ),
+            Some(EngineOption::COMBO {
+                name: "Style".to_string(),
+                value: "Normal".to_string(),
+                vars: vec!["Solid".to_string(), "Normal".to_string(), "Risky Play".to_string()],
+            })
+        );
+        assert_eq!(
+            EngineOption::parse("option name Clear Hash type button"),
+            Some(EngineOption::BUTTON {
+                name: "Clear Hash".to_string(),
+            })
+        );
+        assert_eq!(
+            EngineOption::parse("option name Threads type spin default many min x max 1024"),
+            Some(EngineOption::SPIN {
+                name: "Threads".to_string(),
+                value: 0,
+                min: None,
+                max: Some(1024),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_info_lines() {
+        let info = InfoLine::parse(
+            "info depth 24 seldepth 33 multipv 2 score cp -18 upperbound wdl 40 900 60 nodes 1234567 nps 987654 hashfull 312 tbhits 7 time 1250 pv e2e4 e7e5 g1f3 b8c6",
+        )
+        .unwrap();
+        assert_eq!(info.depth, Some(24));
+        assert_eq!(info.selective_depth, Some(33));
+        assert_eq!(info.multipv, Some(2));
+        assert_eq!(info.score, Some(Score::Cp(-18)));
+        assert_eq!(info.bound, Bound::Upper);
+        assert_eq!(
+            info.wdl,
+            Some(Wdl {
+                win: 40,
+                draw: 900,
+                loss: 60,
+            })
+        );
+        assert_eq!(info.nodes, Some(1234567));
+        assert_eq!(info.nps, Some(987654));
+        assert_eq!(info.hashfull, Some(312));
+        assert_eq!(info.tbhits, Some(7));
+        assert_eq!(info.time, Some(1250));
+        assert_eq!(info.pv, vec!["e2e4", "e7e5", "g1f3", "b8c6"]);
+
+        let info = InfoLine::parse("info depth 5 currmove g1f3 currmovenumber 3 score mate -2 lowerbound").unwrap();
+        assert_eq!(info.current_move.as_deref(), Some("g1f3"));
+        assert_eq!(info.current_move_number, Some(3));
+        assert_eq!(info.score, Some(Score::Mate(-2)));
+        assert_eq!(info.bound, Bound::Lower);
+        assert!(info.pv.is_empty());
+    }
+
+    #[test]
+    fn scales_scores_for_the_eval_bar() {
+        assert_eq!(Score::Cp(0).white_share(true), 0.5);
+        assert!(Score::Cp(300).white_share(true) > 0.7);
+        assert_eq!(Score::Cp(300).white_share(false), 1.0 - Score::Cp(300).white_share(true));
+        assert_eq!(Score::Mate(3).white_share(false), 0.0);
+        assert_eq!(Score::Mate(-2).white_share(false), 1.0);
+        assert_eq!(Score::Mate(0).white_share(false), 1.0);
+        assert_eq!(Score::Cp(-45).for_white(false), Score::Cp(45));
+    }
+
+    #[test]
+    fn writes_pgn_movetext() {
+        init_bishop_magics();
+        init_rook_magics();
+
+        let moves = ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"]
+            .iter()
+            .map(|uci| pgn::PgnMove {
+                uci: uci.to_string(),
+                comment: None,
+            })
+            .collect::<Vec<_>>();
+        let pgn = pgn::write_pgn(&[("White", "A".to_string())], None, &moves, "*");
+        assert_eq!(pgn, "[White \"A\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 *\n\n");
+
+        let pgn = pgn::write_pgn(
+            &[],
+            Some("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"),
+            &moves[1..2],
+            "*",
+        );
+        assert_eq!(pgn, "\n1... e5 *\n\n");
+
+        assert_eq!(
+            TimeControl::Fischer {
+                base: 60_000,
+                increment: 1_000
+            }
+            .pgn_tag(),
+            "60+1"
+        );
+        assert_eq!(
+            TimeControl::MovesToGo {
+                moves: 40,
+                base: 5_400_000,
+                increment: 0
+            }
+            .pgn_tag(),
+            "40/5400"
+        );
+        assert_eq!(TimeControl::FixedDepth(12).pgn_tag(), "-");
+    }
+
+    #[test]
+    fn parses_pgn() {
+        init_bishop_magics();
+        init_rook_magics();
+
+        let text = r#"[Event "Test"]
+[White "A \"B\" C"]
+[Result "1-0"]
+
+{Opening} 1. e4 e5 2. Nf3 $1 (2. f4 exf4 (2... d5) 3. Nf3) 2... Nc6 3. Bb5!? a6
+; rest of line
+4. Ba4 1-0
+
+[Event "Second"]
+
+1. d4 d5 *
+"#;
+        let games = pgn::parse_pgn(text).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let game = &games[0];
+        assert_eq!(game.tag("White"), Some("A \"B\" C"));
+        assert_eq!(game.comment.as_deref(), Some("Opening"));
+        assert_eq!(game.result, "1-0");
+        assert_eq!(
+            game.mainline(),
+            vec!["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6", "b5a4"]
+        );
+        assert_eq!(game.moves[2].nags, vec![1]);
+        assert_eq!(game.moves[4].nags, vec![5]);
+        assert_eq!(game.moves[5].comment.as_deref(), Some("rest of line"));
+
+        let variation = &game.moves[2].variations[0];
+        assert_eq!(variation[0].uci, "f2f4");
+        assert_eq!(variation[1].variations[0][0].uci, "d7d5");
+        assert_eq!(variation[2].uci, "g1f3");
+
+        assert_eq!(games[1].mainline(), vec!["d2d4", "d7d5"]);
+        assert!(pgn::parse_pgn("1. e4 e5 2. Ke3 *").is_err());
+
+        // castling written with zeros is not a move number
+        let games = pgn::parse_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4.0-0 Nf6 5. d3 0-0 *").unwrap();
+        let mainline = games[0].mainline();
+        assert_eq!(mainline[6], "e1g1");
+        assert_eq!(mainline[9], "e8g8");
+    }
+
+    #[test]
+    fn converts_san() {
+        init_bishop_magics();
+        init_rook_magics();
+
+        // white knights on b3 and f3 can both reach d2, the e-pawn promotes on e8 with check
+        let mut board = queenfish::board::Board::new();
+        board.load_from_fen("k7/4P3/8/8/8/1N3N2/8/R3K2R w KQ - 0 1");
+        for (san, uci) in [
+            ("Nbd2", "b3d2"),
+            ("Nfd2", "f3d2"),
+            ("Nb3-d2", "b3d2"),
+            ("O-O", "e1g1"),
+            ("0-0-0", "e1c1"),
+            ("e8=Q+", "e7e8q"),
+            ("e8N", "e7e8n"),
+        ] {
+            let mv = notation::san_to_move(&mut board, san).unwrap();
+            assert_eq!(mv.to_uci(), uci);
+            let back = notation::san_to_move(&mut board, &notation::move_to_san(&mut board, mv));
+            assert_eq!(back.map(|mv| mv.to_uci()), Some(uci.to_string()));
+        }
+        assert!(notation::san_to_move(&mut board, "Nd2").is_none());
+        assert!(notation::san_to_move(&mut board, "N3d2").is_none());
+        assert!(notation::san_to_move(&mut board, "e8").is_none());
+        assert!(notation::san_to_move(&mut board, "Qd4").is_none());
+    }
+
+    #[test]
+    fn writes_variations() {
+        init_bishop_magics();
+        init_rook_magics();
+
+        let game = pgn::parse_pgn("1. e4 e5 (1... c5 2. Nf3 (2. Nc3) 2... d6) 2. Nf3 Nc6 *")
+            .unwrap()
+            .remove(0);
+        let mut tree = game.to_tree();
+        assert_eq!(
+            pgn::write_tree_pgn(&[], None, &tree, "*"),
+            "\n1. e4 e5 (1... c5 2. Nf3 (2. Nc3) 2... d6) 2. Nf3 Nc6 *\n\n"
+        );
+
+        // promote 1... c5 2. Nc3 to the main line, then drop 2. Nf3 from it
+        let e4 = tree.children(tree::ROOT)[0];
+        let c5 = tree.children(e4)[1];
+        let nc3 = tree.children(c5)[1];
+        tree.promote(nc3);
+        assert!(tree.is_mainline(nc3));
+        assert_eq!(
+            pgn::write_tree_pgn(&[], None, &tree, "*"),
+            "\n1. e4 c5 (1... e5 2. Nf3 Nc6) 2. Nc3 (2. Nf3 d6) *\n\n"
+        );
+        let nf3 = tree.children(c5)[1];
+        assert_eq!(tree.delete(nf3), Some(c5));
+        assert_eq!(
+            pgn::write_tree_pgn(&[], None, &tree, "*"),
+            "\n1. e4 c5 (1... e5 2. Nf3 Nc6) 2. Nc3 *\n\n"
+        );
+    }
+
+    #[test]
+    fn keeps_pgn_annotations() {
+        init_bishop_magics();
+        init_rook_magics();
+
+        let game = pgn::parse_pgn("{Opening} 1. e4! {best by test} e5 (1... c5 $2) 2. Nf3 *")
+            .unwrap()
+            .remove(0);
+        let tree = game.to_tree();
+        let e4 = tree.children(tree::ROOT)[0];
+        let c5 = tree.children(e4)[1];
+        assert_eq!(tree.comment(tree::ROOT), Some("Opening"));
+        assert_eq!(tree.comment(e4), Some("best by test"));
+        assert_eq!(tree.nags(e4), &[1]);
+        assert_eq!(tree.nags(c5), &[2]);
+        assert_eq!(
+            pgn::write_tree_pgn(&[], None, &tree, "*"),
+            "\n{Opening} 1. e4 $1 {best by test} 1... e5 (1... c5 $2) 2. Nf3 *\n\n"
+        );
+    }
+
+    #[test]
+    fn merges_keymap_overrides() {
+        let overrides = [
+            ("ctrl-left".to_string(), "PreviousMove".to_string()),
+            ("a".to_string(), "ToggleAnalysis".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let bindings = gui::keymap::merge_bindings(&overrides);
+        assert_eq!(bindings.get("ctrl-left").map(String::as_str), Some("PreviousMove"));
+        assert_eq!(bindings.get("a").map(String::as_str), Some("ToggleAnalysis"));
+        assert_eq!(bindings.get("left"), None);
+        assert_eq!(bindings.get("space"), None);
+        assert_eq!(bindings.get("right").map(String::as_str), Some("NextMove"));
+    }
+
+    #[test]
+    fn saves_engine_registry() {
+        let entry = |path: &str| EngineEntry {
+            name: "stockfish".to_string(),
+            path: path.to_string(),
+            working_directory: None,
+            arguments: Vec::new(),
+            options: Default::default(),
+        };
+        let mut registry = EngineRegistry {
+            engines: vec![entry("/opt/sf16/stockfish"), entry("/opt/sf17/stockfish")],
+        };
+        registry
+            .entry_mut("/opt/sf17/stockfish")
+            .unwrap()
+            .options
+            .insert("Hash".to_string(), "256".to_string());
+        assert!(registry.entry("/opt/sf16/stockfish").unwrap().options.is_empty());
+
+        let path = std::env::temp_dir().join(format!("arena-engines-{}.toml", std::process::id()));
+        registry.save_to(&path).unwrap();
+        let loaded = EngineRegistry::load_from(&path);
+        std::fs::remove_file(&path).ok();
+        assert_eq!(loaded.unwrap(), registry);
+    }
+
+    #[test]
+    fn reads_gui_settings() {
+        let settings: gui::settings::GuiSettings = toml::from_str("animation_ms = 0").unwrap();
+        assert_eq!(settings.animation_duration(), std::time::Duration::ZERO);
+        let settings: gui::settings::GuiSettings = toml::from_str("").unwrap();
+        assert_eq!(settings, gui::settings::GuiSettings::default());
+    }
+
+    #[test]
+    fn aborts_infinite_games() {
+        // never started, the game has to end before the first command
+        let engine = Engine {
+            path: "missing-engine".to_string(),
+            name: "engine".to_string(),
+            engine_options: Vec::new(),
+            engine_handle: None,
+            analysis: Vec::new(),
+            lines: Vec::new(),
+            is_show: true,
+            timeouts: EngineTimeouts::default(),
+            working_directory: None,
+            arguments: Vec::new(),
+        };
+        let mut game = Game::new(engine.clone(), engine, TimeControl::Infinite);
+        let result = game.play();
+        assert_eq!(result.outcome(), Outcome::Unfinished);
+        assert_eq!(result.termination(), Termination::Abort);
+    }
+
+    #[test]
+    fn runs_game_clocks() {
+        let time_control = TimeControl::Fischer {
+            base: 180_000,
+            increment: 2_000,
+        };
+        let mut game = gui::play::VsEngine::new(1, "engine".to_string(), true, time_control, true);
+        assert!(!game.is_engine_turn());
+        game.press_clock();
+        assert!(game.is_engine_turn());
+        let white = game.clock(true).unwrap();
+        // 180 s minus the moment the move took plus the 2 s increment
+        assert!(white > 182_000 - 1_000 && white <= 182_000);
+        assert!(game.go_command().contains("winc 2000"));
+        let timeouts = EngineTimeouts::default();
+        assert!(!game.is_search_overdue(&timeouts));
+        assert_eq!(time_control.search_timeout(&timeouts), None);
+        assert_eq!(
+            TimeControl::TimePerMove(1_000).search_timeout(&timeouts),
+            Some(std::time::Duration::from_millis(1_000) + timeouts.go)
+        );
+        assert_eq!(TimeControl::FixedDepth(10).search_timeout(&timeouts), Some(timeouts.search));
+
+        assert_eq!(pgn::clock_text(181_000), "0:03:01");
+        assert_eq!(pgn::clock_text(9_350), "0:00:09");
+        assert_eq!(pgn::clock_text(-5), "0:00:00");
+        assert_eq!(Termination::Resignation.pgn_tag(), "normal");
+    }
+
+    #[test]
+    fn follows_watched_games() {
+        let time_control = TimeControl::Fischer {
+            base: 60_000,
+            increment: 0,
+        };
+        let (event_tx, event_rx) = std::sync::mpsc::channel();
+        let (command_tx, command_rx) = std::sync::mpsc::channel();
+        let names = ["a".to_string(), "b".to_string()];
+        let mut game = gui::play::WatchedGame::new(1, names, time_control, event_rx, command_tx);
+
+        event_tx
+            .send(GameEvent::Turn {
+                white: false,
+                clocks: [55_000, 58_000],
+            })
+            .unwrap();
+        let event = game.next_event().unwrap();
+        game.apply(&event);
+        assert_eq!(game.thinking(), Some(false));
+        assert_eq!(game.clock(true), Some(55_000));
+        assert!(game.clock(false).unwrap() <= 58_000);
+
+        game.send(GameCommand::Pause);
+        assert!(game.is_paused);
+        assert_eq!(command_rx.try_recv(), Ok(GameCommand::Pause));
+
+        // a game thread that ends without a result leaves the game aborted
+        drop(event_tx);
+        assert!(game.next_event().is_none());
+        assert_eq!(game.result, Some((Outcome::Unfinished, Termination::Abort)));
+        assert_eq!(game.thinking(), None);
+    }
+
+    #[test]
+    fn validates_setup() {
+        init_bishop_magics();
+        init_rook_magics();
+
+        let start = setup::Setup::start();
+        assert_eq!(start.to_fen(), setup::START_FEN);
+        assert_eq!(start.validate(), Ok(()));
+
+        let mut position = setup::Setup::from_fen("4k3/8/8/3pP3/8/8/8/4K2R w K d6 0 1").unwrap();
+        assert_eq!(position.en_passant_candidates(), vec![43]);
+        assert_eq!(position.validate(), Ok(()));
+
+        position.castling[1] = true;
+        assert_eq!(position.validate(), Err(setup::SetupError::CastlingRights));
+        position.castling[1] = false;
+
+        position.pieces[0] = Some(6);
+        assert_eq!(position.validate(), Err(setup::SetupError::PawnOnBackRank));
+        position.pieces[0] = None;
+
+        // a black rook checking the white king with black to move
+        position.white_to_move = false;
+        position.en_passant = None;
+        position.pieces[7] = None;
+        position.castling[0] = false;
+        position.pieces[4 * 8 + 4] = Some(9);
+        assert_eq!(position.validate(), Err(setup::SetupError::OpponentInCheck));
+
+        position.pieces[60] = None;
+        assert_eq!(
+            position.validate(),
+            Err(setup::SetupError::KingCount { white: 1, black: 0 })
+        );
     }
 } //
@@ -0,0 +1,180 @@
+use queenfish::board::{Board, Move};
+
+const PIECE_LETTERS: [&str; 6] = ["", "N", "B", "R", "Q", "K"];
+
+/// 0 pawn, 1 knight, 2 bishop, 3 rook, 4 queen, 5 king, regardless of colour
+fn piece_kind(board: &Board, square: usize) -> Option<usize> {
+    board.piece_at[square].map(|piece| piece as usize % 6)
+}
+
+fn file_char(square: usize) -> char {
+    (b'a' + (square % 8) as u8) as char
+}
+
+fn rank_char(square: usize) -> char {
+    (b'1' + (square / 8) as u8) as char
+}
+
+pub fn find_uci_move(board: &mut Board, uci: &str) -> Option<Move> {
+    board
+        .generate_moves()
+        .iter()
+        .find(|mv| mv.to_uci() == uci)
+        .copied()
+}
+
+/// Converts a legal move in the current position to SAN, e.g. "Nbd7", "exd5", "O-O" or "e8=Q+"
+pub fn move_to_san(board: &mut Board, mv: Move) -> String {
+    let from = mv.from();
+    let to = mv.to();
+    let uci = mv.to_uci();
+    let Some(kind) = piece_kind(board, from) else {
+        return uci;
+    };
+
+    let mut san = String::new();
+    if kind == 5 && from.abs_diff(to) == 2 {
+        san.push_str(if to > from { "O-O" } else { "O-O-O" });
+    } else {
+        // a pawn changing file onto an empty square is an en passant capture
+        let is_capture = board.piece_at[to].is_some() || (kind == 0 && from % 8 != to % 8);
+        if kind == 0 {
+            if is_capture {
+                san.push(file_char(from));
+            }
+        } else {
+            san.push_str(PIECE_LETTERS[kind]);
+            let others = board
+                .generate_moves()
+                .iter()
+                .filter(|other| {
+                    other.to() == to
+                        && other.from() != from
+                        && piece_kind(board, other.from()) == Some(kind)
+                })
+                .map(|other| other.from())
+                .collect::<Vec<_>>();
+            if !others.is_empty() {
+                if others.iter().all(|&square| square % 8 != from % 8) {
+                    san.push(file_char(from));
+                } else if others.iter().all(|&square| square / 8 != from / 8) {
+                    san.push(rank_char(from));
+                } else {
+                    san.push(file_char(from));
+                    san.push(rank_char(from));
+                }
+            }
+        }
+        if is_capture {
+            san.push('x');
+        }
+        san.push(file_char(to));
+        san.push(rank_char(to));
+        if let Some(promotion) = uci.chars().nth(4) {
+            san.push('=');
+            san.push(promotion.to_ascii_uppercase());
+        }
+    }
+
+    let unmake = board.make_move(mv);
+    if board.is_king_in_check(board.turn) {
+        san.push(if board.generate_moves().is_empty() { '#' } else { '+' });
+    }
+    board.unmake_move(unmake);
+    san
+} //
+
+fn square(file: char, rank: char) -> Option<usize> {
+    if !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+        return None;
+    }
+    Some((rank as u8 - b'1') as usize * 8 + (file as u8 - b'a') as usize)
+}
+
+/// Finds the legal move written in SAN. Check marks and annotations are optional, and
+/// "0-0", "e8Q" and long forms such as "Ng1-f3" are accepted. Returns None for an illegal
+/// or ambiguous move.
+pub fn san_to_move(board: &mut Board, san: &str) -> Option<Move> {
+    let san = san.trim().trim_end_matches(['+', '#', '!', '?']);
+    let moves = board.generate_moves().iter().copied().collect::<Vec<_>>();
+
+    let castling = san.replace('0', "O");
+    if castling == "O-O" || castling == "O-O-O" {
+        let is_short = castling == "O-O";
+        return moves.into_iter().find(|mv| {
+            piece_kind(board, mv.from()) == Some(5)
+                && mv.from().abs_diff(mv.to()) == 2
+                && (mv.to() > mv.from()) == is_short
+        });
+    }
+
+    let mut chars = san
+        .chars()
+        .filter(|c| !matches!(c, 'x' | ':' | '-' | '='))
+        .collect::<Vec<_>>();
+    let kind = match chars.first() {
+        Some('N') => 1,
+        Some('B') => 2,
+        Some('R') => 3,
+        Some('Q') => 4,
+        Some('K') => 5,
+        _ => 0,
+    };
+    if kind != 0 {
+        chars.remove(0);
+    }
+    // the target square ends in a digit, so a trailing letter on a pawn move is a promotion
+    let promotion = match chars.last() {
+        Some(c) if kind == 0 && c.is_ascii_alphabetic() => Some(c.to_ascii_lowercase()),
+        _ => None,
+    };
+    if promotion.is_some() {
+        chars.pop();
+    }
+    if chars.len() < 2 {
+        return None;
+    }
+    let to = square(chars[chars.len() - 2], chars[chars.len() - 1])?;
+    let disambiguation = &chars[..chars.len() - 2];
+    let from_file = disambiguation
+        .iter()
+        .find(|c| ('a'..='h').contains(*c))
+        .map(|&c| (c as u8 - b'a') as usize);
+    let from_rank = disambiguation
+        .iter()
+        .find(|c| ('1'..='8').contains(*c))
+        .map(|&c| (c as u8 - b'1') as usize);
+
+    let candidates = moves
+        .into_iter()
+        .filter(|mv| {
+            mv.to() == to
+                && piece_kind(board, mv.from()) == Some(kind)
+                && from_file.is_none_or(|file| mv.from() % 8 == file)
+                && from_rank.is_none_or(|rank| mv.from() / 8 == rank)
+                && mv.to_uci().chars().nth(4) == promotion
+        })
+        .collect::<Vec<_>>();
+    match candidates.as_slice() {
+        [mv] => Some(*mv),
+        _ => None,
+    }
+} //
+
+/// Converts a line of UCI moves to SAN, stopping at the first move that is not legal.
+/// The board is left in the position it started from.
+pub fn pv_to_san(board: &mut Board, pv: &[String]) -> Vec<String> {
+    let mut sans = Vec::new();
+    let mut unmakes = Vec::new();
+    for uci in pv {
+        let Some(mv) = find_uci_move(board, uci) else {
+            break;
+        };
+        sans.push(move_to_san(board, mv));
+        unmakes.push(board.make_move(mv));
+    }
+    for unmake in unmakes.into_iter().rev() {
+        board.unmake_move(unmake);
+    }
+    sans
+} //
@@ -0,0 +1,751 @@
+use crate::engine::Score;
+use crate::game::{GameResult, Outcome, Termination, TimeControl};
+use crate::notation::{find_uci_move, move_to_san, san_to_move};
+use crate::tree::{GameTree, ROOT};
+use queenfish::board::{Board, Move, Turn, UnMakeMove};
+use std::fmt;
+use std::iter::Peekable;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const LINE_WIDTH: usize = 80;
+
+#[derive(Debug)]
+pub enum PgnError {
+    UnterminatedComment,
+    UnterminatedTag,
+    UnbalancedVariation { game: usize },
+    IllegalMove { game: usize, san: String },
+}
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::UnterminatedComment => write!(f, "PGN comment is missing its closing brace"),
+            PgnError::UnterminatedTag => write!(f, "PGN tag is missing its closing bracket"),
+            PgnError::UnbalancedVariation { game } => {
+                write!(f, "Unbalanced variation parentheses in game {}", game)
+            }
+            PgnError::IllegalMove { game, san } => {
+                write!(f, "Illegal or ambiguous move \"{}\" in game {}", san, game)
+            }
+        }
+    }
+}
+impl std::error::Error for PgnError {}
+
+/// A move read from a PGN file, with the annotations that followed it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnNode {
+    pub uci: String,
+    pub san: String,
+    pub comment: Option<String>,
+    pub nags: Vec<u8>,
+    /// alternatives to this move, each starting from the position before it
+    pub variations: Vec<Vec<PgnNode>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    /// comment found before the first move
+    pub comment: Option<String>,
+    pub moves: Vec<PgnNode>,
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    } //
+
+    pub fn start_fen(&self) -> Option<&str> {
+        self.tag("FEN")
+    } //
+
+    /// UCI moves of the main line
+    pub fn mainline(&self) -> Vec<String> {
+        self.moves.iter().map(|node| node.uci.clone()).collect()
+    } //
+
+    /// The game with its variations, comments and NAGs as a `GameTree`
+    pub fn to_tree(&self) -> GameTree {
+        let (mut board, _) = start_board(self.start_fen());
+        let mut tree = GameTree::new();
+        tree.annotate(ROOT, self.comment.clone(), Vec::new());
+        add_pgn_line(&mut tree, &mut board, ROOT, &self.moves);
+        tree
+    } //
+}
+
+fn add_pgn_line(tree: &mut GameTree, board: &mut Board, parent: usize, line: &[PgnNode]) {
+    let mut parent = parent;
+    let mut unmakes = Vec::new();
+    for node in line {
+        let Some(mv) = find_uci_move(board, &node.uci) else {
+            break;
+        };
+        // the main move goes in first so it stays the first child
+        let child = tree.add_move(parent, mv);
+        tree.annotate(child, node.comment.clone(), node.nags.clone());
+        for variation in &node.variations {
+            add_pgn_line(tree, board, parent, variation);
+        }
+        unmakes.push(board.make_move(mv));
+        parent = child;
+    }
+    for unmake in unmakes.into_iter().rev() {
+        board.unmake_move(unmake);
+    }
+} //
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    Open,
+    Close,
+    Result(String),
+    San(String),
+}
+
+fn push_comment(comment: &mut Option<String>, text: String) {
+    match comment {
+        Some(comment) => {
+            comment.push(' ');
+            comment.push_str(&text);
+        }
+        None => *comment = Some(text),
+    }
+} //
+
+fn tokenize(text: &str) -> Result<Vec<Token>, PgnError> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        // a '%' in the first column escapes the whole line
+        if c == '%' && (i == 0 || chars[i - 1] == '\n') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '[' => {
+                let start = i + 1;
+                let mut in_quotes = false;
+                i = start;
+                while i < chars.len() && (in_quotes || chars[i] != ']') {
+                    if chars[i] == '\\' && in_quotes {
+                        i += 1;
+                    } else if chars[i] == '"' {
+                        in_quotes = !in_quotes;
+                    }
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err(PgnError::UnterminatedTag);
+                }
+                let inner = chars[start..i].iter().collect::<String>();
+                i += 1;
+                let inner = inner.trim();
+                let name = inner.split_whitespace().next().unwrap_or_default().to_string();
+                let value = match (inner.find('"'), inner.rfind('"')) {
+                    (Some(open), Some(close)) if close > open => &inner[open + 1..close],
+                    _ => "",
+                };
+                let value = value.replace("\\\"", "\"").replace("\\\\", "\\");
+                tokens.push(Token::Tag(name, value));
+            }
+            '{' => {
+                let start = i + 1;
+                let Some(end) = chars[start..].iter().position(|&c| c == '}') else {
+                    return Err(PgnError::UnterminatedComment);
+                };
+                let comment = chars[start..start + end].iter().collect::<String>();
+                // comments may span lines, collapse them to single spaces
+                let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+                tokens.push(Token::Comment(comment));
+                i = start + end + 1;
+            }
+            ';' => {
+                let start = i + 1;
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                let comment = chars[start..i].iter().collect::<String>();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            '(' => {
+                tokens.push(Token::Open);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                i += 1;
+            }
+            '$' => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let nag = chars[start..i].iter().collect::<String>();
+                if let Ok(nag) = nag.parse() {
+                    tokens.push(Token::Nag(nag));
+                }
+            }
+            _ => {
+                let start = i;
+                while i < chars.len()
+                    && !chars[i].is_whitespace()
+                    && !"[]{}();$".contains(chars[i])
+                {
+                    i += 1;
+                }
+                let word = chars[start..i].iter().collect::<String>();
+                tokens.extend(word_tokens(&word));
+            }
+        }
+    }
+    Ok(tokens)
+} //
+
+/// Splits a movetext word such as "12.Nf3!?" into a SAN and its NAG, dropping the move number
+fn word_tokens(word: &str) -> Vec<Token> {
+    if matches!(word, "1-0" | "0-1" | "1/2-1/2" | "*") {
+        return vec![Token::Result(word.to_string())];
+    }
+    // a move number is digits followed by dots, castling may also be written with zeros
+    let mut word = word;
+    let after_digits = word.trim_start_matches(|c: char| c.is_ascii_digit());
+    if after_digits.is_empty() || (after_digits.len() < word.len() && after_digits.starts_with('.')) {
+        word = after_digits.trim_start_matches('.');
+    }
+    if word.is_empty() {
+        return Vec::new();
+    }
+
+    let san = word.trim_end_matches(['!', '?']);
+    let nag = match &word[san.len()..] {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    };
+    let mut tokens = vec![Token::San(san.to_string())];
+    tokens.extend(nag.map(Token::Nag));
+    tokens
+} //
+
+struct Line {
+    moves: Vec<PgnNode>,
+    unmakes: Vec<UnMakeMove>,
+    /// the move taken back to start this variation, played again when it closes
+    replay: Option<Move>,
+    pending_comment: Option<String>,
+}
+
+impl Line {
+    fn new(replay: Option<Move>) -> Self {
+        Line {
+            moves: Vec::new(),
+            unmakes: Vec::new(),
+            replay,
+            pending_comment: None,
+        }
+    } //
+}
+
+fn parse_game<I: Iterator<Item = Token>>(
+    tokens: &mut Peekable<I>,
+    game_number: usize,
+) -> Result<Option<PgnGame>, PgnError> {
+    let mut tags = Vec::new();
+    while let Some(Token::Tag(..)) = tokens.peek() {
+        if let Some(Token::Tag(name, value)) = tokens.next() {
+            tags.push((name, value));
+        }
+    }
+
+    let mut board = Board::new();
+    if let Some((_, fen)) = tags.iter().find(|(name, _)| name == "FEN") {
+        board.load_from_fen(fen);
+    }
+
+    let mut lines = vec![Line::new(None)];
+    let mut result = None;
+    let mut has_content = !tags.is_empty();
+    while let Some(token) = tokens.peek() {
+        // a tag after movetext belongs to the next game
+        if let Token::Tag(..) = token {
+            break;
+        }
+        let Some(token) = tokens.next() else {
+            break;
+        };
+        has_content = true;
+        let is_variation = lines.len() > 1;
+        let line = lines.last_mut().unwrap();
+        match token {
+            Token::Tag(..) => {}
+            Token::San(san) => {
+                let Some(mv) = san_to_move(&mut board, &san) else {
+                    return Err(PgnError::IllegalMove {
+                        game: game_number,
+                        san,
+                    });
+                };
+                let node = PgnNode {
+                    uci: mv.to_uci(),
+                    san: move_to_san(&mut board, mv),
+                    // before the first main line move it stays the game comment
+                    comment: if is_variation {
+                        line.pending_comment.take()
+                    } else {
+                        None
+                    },
+                    nags: Vec::new(),
+                    variations: Vec::new(),
+                };
+                line.unmakes.push(board.make_move(mv));
+                line.moves.push(node);
+            }
+            Token::Comment(comment) => match line.moves.last_mut() {
+                Some(node) => push_comment(&mut node.comment, comment),
+                None => push_comment(&mut line.pending_comment, comment),
+            },
+            Token::Nag(nag) => {
+                if let Some(node) = line.moves.last_mut() {
+                    node.nags.push(nag);
+                }
+            }
+            Token::Open => {
+                let (Some(unmake), Some(node)) = (line.unmakes.pop(), line.moves.last()) else {
+                    return Err(PgnError::UnbalancedVariation { game: game_number });
+                };
+                let Some(mv) = find_uci_move_before(&mut board, unmake, &node.uci) else {
+                    return Err(PgnError::UnbalancedVariation { game: game_number });
+                };
+                lines.push(Line::new(Some(mv)));
+            }
+            Token::Close => {
+                if lines.len() < 2 {
+                    return Err(PgnError::UnbalancedVariation { game: game_number });
+                }
+                let variation = lines.pop().unwrap();
+                for unmake in variation.unmakes.into_iter().rev() {
+                    board.unmake_move(unmake);
+                }
+                let parent = lines.last_mut().unwrap();
+                if let Some(mv) = variation.replay {
+                    parent.unmakes.push(board.make_move(mv));
+                }
+                if !variation.moves.is_empty() {
+                    if let Some(node) = parent.moves.last_mut() {
+                        node.variations.push(variation.moves);
+                    }
+                }
+            }
+            Token::Result(text) => {
+                result = Some(text);
+                break;
+            }
+        }
+    }
+
+    if lines.len() > 1 {
+        return Err(PgnError::UnbalancedVariation { game: game_number });
+    }
+    if !has_content {
+        return Ok(None);
+    }
+    let root = lines.pop().unwrap();
+    let result = result
+        .or_else(|| {
+            tags.iter()
+                .find(|(name, _)| name == "Result")
+                .map(|(_, value)| value.clone())
+        })
+        .unwrap_or_else(|| "*".to_string());
+    Ok(Some(PgnGame {
+        tags,
+        comment: root.pending_comment,
+        moves: root.moves,
+        result,
+    }))
+} //
+
+/// Takes back `unmake` and returns the move it undid, looked up in the restored position
+fn find_uci_move_before(board: &mut Board, unmake: UnMakeMove, uci: &str) -> Option<Move> {
+    board.unmake_move(unmake);
+    find_uci_move(board, uci)
+} //
+
+/// Reads every game of a PGN file. Variations are kept, but a move that cannot be
+/// resolved in any line fails the whole file.
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut tokens = tokenize(text)?.into_iter().peekable();
+    let mut games = Vec::new();
+    while tokens.peek().is_some() {
+        if let Some(game) = parse_game(&mut tokens, games.len() + 1)? {
+            games.push(game);
+        }
+    }
+    Ok(games)
+} //
+
+pub struct PgnMove {
+    pub uci: String,
+    /// written between braces after the move, without the braces
+    pub comment: Option<String>,
+}
+
+/// Writes a single game. Moves are replayed from `start_fen` (or the standard start) to
+/// produce SAN, and writing stops at the first move that is not legal.
+pub fn write_pgn(
+    tags: &[(&str, String)],
+    start_fen: Option<&str>,
+    moves: &[PgnMove],
+    result: &str,
+) -> String {
+    let (mut board, mut move_number) = start_board(start_fen);
+
+    let mut tokens = Vec::new();
+    let mut is_first_move = true;
+    for pgn_move in moves {
+        let Some(mv) = find_uci_move(&mut board, &pgn_move.uci) else {
+            break;
+        };
+        match board.turn {
+            Turn::WHITE => tokens.push(format!("{}.", move_number)),
+            Turn::BLACK if is_first_move => tokens.push(format!("{}...", move_number)),
+            Turn::BLACK => {}
+        }
+        tokens.push(move_to_san(&mut board, mv));
+        if let Some(comment) = &pgn_move.comment {
+            tokens.push(format!("{{{}}}", comment));
+        }
+        if let Turn::BLACK = board.turn {
+            move_number += 1;
+        }
+        board.make_move(mv);
+        is_first_move = false;
+    }
+    tokens.push(result.to_string());
+
+    write_tags(tags) + &wrap_movetext(tokens)
+} //
+
+/// The start position and the number of its first move
+fn start_board(start_fen: Option<&str>) -> (Board, u32) {
+    let mut board = Board::new();
+    let mut move_number = 1;
+    if let Some(fen) = start_fen {
+        board.load_from_fen(fen);
+        move_number = fen
+            .split_whitespace()
+            .nth(5)
+            .and_then(|number| number.parse::<u32>().ok())
+            .unwrap_or(1);
+    }
+    (board, move_number)
+} //
+
+fn write_tags(tags: &[(&str, String)]) -> String {
+    let mut pgn = String::new();
+    for (name, value) in tags {
+        pgn.push_str(&format!(
+            "[{} \"{}\"]\n",
+            name,
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        ));
+    }
+    pgn.push('\n');
+    pgn
+} //
+
+/// Joins movetext tokens into lines within the 80 columns the PGN standard asks for
+fn wrap_movetext(tokens: Vec<String>) -> String {
+    let mut pgn = String::new();
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > LINE_WIDTH {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push_str("\n\n");
+    pgn
+} //
+
+/// Writes the moves after `parent` along its main line, every side variation in parentheses
+/// right after the move it replaces. The board is left where it started.
+fn write_tree_moves(
+    board: &mut Board,
+    tree: &GameTree,
+    parent: usize,
+    move_number: u32,
+    needs_number: bool,
+    tokens: &mut Vec<String>,
+) {
+    let mut parent = parent;
+    let mut move_number = move_number;
+    let mut needs_number = needs_number;
+    let mut unmakes = Vec::new();
+    while let Some((&main, alternatives)) = tree.children(parent).split_first() {
+        let Some(mv) = tree.mv(main) else {
+            break;
+        };
+        let is_white = matches!(board.turn, Turn::WHITE);
+        push_numbered_move(board, mv, move_number, needs_number, tokens);
+        push_annotations(tree, main, tokens);
+        // black's reply after a comment needs its number repeated
+        needs_number = tree.comment(main).is_some();
+
+        for &alternative in alternatives {
+            let Some(alternative_mv) = tree.mv(alternative) else {
+                continue;
+            };
+            let start = tokens.len();
+            push_numbered_move(board, alternative_mv, move_number, true, tokens);
+            push_annotations(tree, alternative, tokens);
+            let unmake = board.make_move(alternative_mv);
+            let next_number = if is_white { move_number } else { move_number + 1 };
+            let needs_number = tree.comment(alternative).is_some();
+            write_tree_moves(board, tree, alternative, next_number, needs_number, tokens);
+            board.unmake_move(unmake);
+            tokens[start] = format!("({}", tokens[start]);
+            if let Some(last) = tokens.last_mut() {
+                last.push(')');
+            }
+            // black's reply after a variation needs its number repeated
+            needs_number = true;
+        }
+
+        unmakes.push(board.make_move(mv));
+        if !is_white {
+            move_number += 1;
+        }
+        parent = main;
+    }
+    for unmake in unmakes.into_iter().rev() {
+        board.unmake_move(unmake);
+    }
+} //
+
+fn push_numbered_move(
+    board: &mut Board,
+    mv: Move,
+    move_number: u32,
+    needs_number: bool,
+    tokens: &mut Vec<String>,
+) {
+    match board.turn {
+        Turn::WHITE => tokens.push(format!("{}.", move_number)),
+        Turn::BLACK if needs_number => tokens.push(format!("{}...", move_number)),
+        Turn::BLACK => {}
+    }
+    tokens.push(move_to_san(board, mv));
+} //
+
+fn push_annotations(tree: &GameTree, node: usize, tokens: &mut Vec<String>) {
+    tokens.extend(tree.nags(node).iter().map(|nag| format!("${}", nag)));
+    if let Some(comment) = tree.comment(node) {
+        tokens.push(format!("{{{}}}", comment));
+    }
+} //
+
+/// Writes a whole game tree, side variations, comments and NAGs included
+pub fn write_tree_pgn(
+    tags: &[(&str, String)],
+    start_fen: Option<&str>,
+    tree: &GameTree,
+    result: &str,
+) -> String {
+    let (mut board, move_number) = start_board(start_fen);
+    let mut tokens = Vec::new();
+    if let Some(comment) = tree.comment(ROOT) {
+        tokens.push(format!("{{{}}}", comment));
+    }
+    write_tree_moves(&mut board, tree, ROOT, move_number, true, &mut tokens);
+    tokens.push(result.to_string());
+    write_tags(tags) + &wrap_movetext(tokens)
+} //
+
+/// Exports a game played by `Game::play`. `round` fills the Round tag and `with_comments`
+/// adds `{[%eval …] [%clk …]}` after every move the engines reported data for.
+pub fn game_result_to_pgn(game: &GameResult, round: Option<u32>, with_comments: bool) -> String {
+    let mut tags = vec![
+        ("Event", "Arena engine match".to_string()),
+        ("Site", "?".to_string()),
+        ("Date", pgn_date()),
+        ("Round", round.map_or("?".to_string(), |round| round.to_string())),
+        ("White", game.white().to_string()),
+        ("Black", game.black().to_string()),
+        ("Result", game.result_string().to_string()),
+    ];
+    if let Some(fen) = game.start_fen() {
+        tags.push(("SetUp", "1".to_string()));
+        tags.push(("FEN", fen.to_string()));
+    }
+    tags.push(("TimeControl", game.time_control().pgn_tag()));
+    tags.push(("Termination", game.termination().pgn_tag().to_string()));
+
+    // black moves first when the start position says so
+    let black_starts = game
+        .start_fen()
+        .and_then(|fen| fen.split_whitespace().nth(1))
+        .is_some_and(|turn| turn == "b");
+    let moves = game
+        .moves()
+        .iter()
+        .enumerate()
+        .map(|(ply, record)| {
+            let is_white = (ply % 2 == 0) != black_starts;
+            let mut comment = Vec::new();
+            if with_comments {
+                if let Some(score) = record.score {
+                    comment.push(format!("[%eval {}]", eval_text(score, is_white)));
+                }
+                if let Some(clock) = record.clock {
+                    comment.push(format!("[%clk {}]", clock_text(clock)));
+                }
+            }
+            PgnMove {
+                uci: record.uci.clone(),
+                comment: (!comment.is_empty()).then(|| comment.join(" ")),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    write_pgn(&tags, game.start_fen(), &moves, game.result_string())
+} //
+
+/// Exports moves played on a board, e.g. the GUI history. The result is read from the
+/// position the moves lead to.
+pub fn moves_to_pgn(start_fen: Option<&str>, moves: &[Move]) -> String {
+    let result = final_result(start_fen, moves);
+    let tags = analysis_tags(start_fen, result);
+    let moves = moves
+        .iter()
+        .map(|mv| PgnMove {
+            uci: mv.to_uci(),
+            comment: None,
+        })
+        .collect::<Vec<_>>();
+    write_pgn(&tags, start_fen, &moves, result)
+} //
+
+/// Like `moves_to_pgn` for a game tree, the result is read from the end of the main line
+pub fn tree_to_pgn(start_fen: Option<&str>, tree: &GameTree) -> String {
+    let result = final_result(start_fen, &tree.line_moves(ROOT));
+    let tags = analysis_tags(start_fen, result);
+    write_tree_pgn(&tags, start_fen, tree, result)
+} //
+
+/// Exports a game played in the GUI, `players` being the white and black names
+pub fn played_game_to_pgn(
+    players: [&str; 2],
+    start_fen: Option<&str>,
+    tree: &GameTree,
+    time_control: TimeControl,
+    outcome: Outcome,
+    termination: Termination,
+) -> String {
+    let result = outcome.result_string();
+    let mut tags = vec![
+        ("Event", "Arena game".to_string()),
+        ("Site", "?".to_string()),
+        ("Date", pgn_date()),
+        ("Round", "-".to_string()),
+        ("White", players[0].to_string()),
+        ("Black", players[1].to_string()),
+        ("Result", result.to_string()),
+    ];
+    if let Some(fen) = start_fen {
+        tags.push(("SetUp", "1".to_string()));
+        tags.push(("FEN", fen.to_string()));
+    }
+    tags.push(("TimeControl", time_control.pgn_tag()));
+    tags.push(("Termination", termination.pgn_tag().to_string()));
+    write_tree_pgn(&tags, start_fen, tree, result)
+} //
+
+fn final_result(start_fen: Option<&str>, moves: &[Move]) -> &'static str {
+    let (mut board, _) = start_board(start_fen);
+    for mv in moves {
+        board.make_move(*mv);
+    }
+    match board.game_result() {
+        queenfish::board::GameResult::WhiteWin => Outcome::WhiteWin,
+        queenfish::board::GameResult::BlackWin => Outcome::BlackWin,
+        queenfish::board::GameResult::Draw(_) => Outcome::Draw,
+        queenfish::board::GameResult::InProgress => Outcome::Unfinished,
+    }
+    .result_string()
+} //
+
+fn analysis_tags(start_fen: Option<&str>, result: &str) -> Vec<(&'static str, String)> {
+    let mut tags = vec![
+        ("Event", "Arena analysis".to_string()),
+        ("Site", "?".to_string()),
+        ("Date", pgn_date()),
+        ("Round", "-".to_string()),
+        ("White", "?".to_string()),
+        ("Black", "?".to_string()),
+        ("Result", result.to_string()),
+    ];
+    if let Some(fen) = start_fen {
+        tags.push(("SetUp", "1".to_string()));
+        tags.push(("FEN", fen.to_string()));
+    }
+    tags
+} //
+
+/// Engine scores are from the mover's side, PGN evals from white's side in pawns
+fn eval_text(score: Score, is_white: bool) -> String {
+    let sign = if is_white { 1 } else { -1 };
+    match score {
+        Score::Cp(cp) => format!("{:.2}", (cp * sign) as f64 / 100.0),
+        Score::Mate(moves) => format!("#{}", moves * sign),
+    }
+} //
+
+/// "h:mm:ss", as in `%clk` comments and the game clocks
+pub fn clock_text(ms: i64) -> String {
+    let seconds = ms.max(0) / 1000;
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+} //
+
+/// Today's date as "YYYY.MM.DD"
+fn pgn_date() -> String {
+    let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) else {
+        return "????.??.??".to_string();
+    };
+    // civil-from-days conversion, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (now.as_secs() / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}.{:02}.{:02}", year, month, day)
+} //
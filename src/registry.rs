@@ -0,0 +1,124 @@
+use crate::engine::{Engine, EngineError, EngineTimeouts};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum RegistryError {
+    NoConfigDir,
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::NoConfigDir => write!(f, "No user config directory is available"),
+            RegistryError::Io(err) => write!(f, "Engine registry I/O error: {}", err),
+            RegistryError::Parse(err) => write!(f, "Engine registry is not valid TOML: {}", err),
+            RegistryError::Serialize(err) => {
+                write!(f, "Failed to serialize engine registry: {}", err)
+            }
+        }
+    }
+}
+impl std::error::Error for RegistryError {}
+
+/// One engine as written in `engines.toml`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EngineEntry {
+    pub name: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_directory: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<String>,
+    /// option name -> value, applied with `setoption` every time the engine is loaded
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub options: BTreeMap<String, String>,
+}
+
+impl EngineEntry {
+    pub fn from_engine(engine: &Engine) -> Self {
+        EngineEntry {
+            name: engine.name.clone(),
+            path: engine.path.clone(),
+            working_directory: engine.working_directory.clone(),
+            arguments: engine.arguments.clone(),
+            options: BTreeMap::new(),
+        }
+    } //
+
+    /// Starts the engine and applies the saved option overrides
+    pub fn load_engine(&self) -> Result<Engine, EngineError> {
+        let mut engine = Engine::with_config(
+            &self.path,
+            &self.name,
+            self.working_directory.clone(),
+            self.arguments.clone(),
+            EngineTimeouts::default(),
+        )?;
+        for (name, value) in &self.options {
+            let option = engine
+                .engine_options
+                .iter()
+                .find(|option| option.name() == name)
+                .and_then(|option| option.with_value(value));
+            if let Some(option) = option {
+                engine.set_option(option);
+            }
+        }
+        Ok(engine)
+    } //
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EngineRegistry {
+    #[serde(default)]
+    pub engines: Vec<EngineEntry>,
+}
+
+impl EngineRegistry {
+    /// `<config dir>/arena/engines.toml`
+    pub fn config_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("arena").join("engines.toml"))
+    } //
+
+    pub fn load() -> Result<Self, RegistryError> {
+        let path = EngineRegistry::config_path().ok_or(RegistryError::NoConfigDir)?;
+        EngineRegistry::load_from(&path)
+    } //
+
+    /// A missing file is an empty registry
+    pub fn load_from(path: &Path) -> Result<Self, RegistryError> {
+        if !path.exists() {
+            return Ok(EngineRegistry::default());
+        }
+        let content = fs::read_to_string(path).map_err(RegistryError::Io)?;
+        toml::from_str(&content).map_err(RegistryError::Parse)
+    } //
+
+    pub fn save(&self) -> Result<(), RegistryError> {
+        let path = EngineRegistry::config_path().ok_or(RegistryError::NoConfigDir)?;
+        self.save_to(&path)
+    } //
+
+    pub fn save_to(&self, path: &Path) -> Result<(), RegistryError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(RegistryError::Io)?;
+        }
+        let content = toml::to_string_pretty(self).map_err(RegistryError::Serialize)?;
+        fs::write(path, content).map_err(RegistryError::Io)
+    } //
+
+    /// Entries are told apart by path, engines in different folders often share a name
+    pub fn entry(&self, path: &str) -> Option<&EngineEntry> {
+        self.engines.iter().find(|entry| entry.path == path)
+    } //
+
+    pub fn entry_mut(&mut self, path: &str) -> Option<&mut EngineEntry> {
+        self.engines.iter_mut().find(|entry| entry.path == path)
+    } //
+}
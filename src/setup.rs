@@ -0,0 +1,212 @@
+use queenfish::board::{Board, Turn};
+use std::fmt;
+
+/// FEN letters by piece index, 0-5 white pawn to king and 6-11 black
+const PIECE_CHARS: [char; 12] = ['P', 'N', 'B', 'R', 'Q', 'K', 'p', 'n', 'b', 'r', 'q', 'k'];
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetupError {
+    KingCount { white: usize, black: usize },
+    TooManyPawns,
+    PawnOnBackRank,
+    OpponentInCheck,
+    CastlingRights,
+    EnPassant,
+}
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetupError::KingCount { white, black } => write!(
+                f,
+                "Each side needs exactly one king, found {} white and {} black",
+                white, black
+            ),
+            SetupError::TooManyPawns => write!(f, "A side has more than 8 pawns"),
+            SetupError::PawnOnBackRank => write!(f, "Pawns cannot stand on the first or last rank"),
+            SetupError::OpponentInCheck => write!(f, "The side not to move is in check"),
+            SetupError::CastlingRights => {
+                write!(f, "Castling rights need the king and rook on their start squares")
+            }
+            SetupError::EnPassant => write!(f, "No pawn can be captured en passant on that square"),
+        }
+    }
+}
+impl std::error::Error for SetupError {}
+
+/// A position being set up by hand
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setup {
+    /// piece index by square, a1 = 0
+    pub pieces: [Option<usize>; 64],
+    pub white_to_move: bool,
+    /// white king side, white queen side, black king side, black queen side
+    pub castling: [bool; 4],
+    pub en_passant: Option<usize>,
+}
+
+impl Setup {
+    pub fn empty() -> Self {
+        Setup {
+            pieces: [None; 64],
+            white_to_move: true,
+            castling: [false; 4],
+            en_passant: None,
+        }
+    } //
+
+    pub fn start() -> Self {
+        Setup::from_fen(START_FEN).unwrap_or_else(Setup::empty)
+    } //
+
+    /// Reads the first four FEN fields, the move counters are ignored
+    pub fn from_fen(fen: &str) -> Option<Self> {
+        let mut fields = fen.split_whitespace();
+        let mut setup = Setup::empty();
+
+        let ranks = fields.next()?.split('/').collect::<Vec<_>>();
+        if ranks.len() != 8 {
+            return None;
+        }
+        for (row, rank) in ranks.iter().enumerate() {
+            let mut file = 0;
+            for c in rank.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    file += empty as usize;
+                    continue;
+                }
+                let piece = PIECE_CHARS.iter().position(|&piece| piece == c)?;
+                if file > 7 {
+                    return None;
+                }
+                setup.pieces[(7 - row) * 8 + file] = Some(piece);
+                file += 1;
+            }
+            if file != 8 {
+                return None;
+            }
+        }
+
+        setup.white_to_move = fields.next().unwrap_or("w") != "b";
+        let castling = fields.next().unwrap_or("-");
+        for (index, right) in ['K', 'Q', 'k', 'q'].into_iter().enumerate() {
+            setup.castling[index] = castling.contains(right);
+        }
+        setup.en_passant = fields.next().and_then(square_index);
+        Some(setup)
+    } //
+
+    pub fn to_fen(&self) -> String {
+        let mut placement = Vec::new();
+        for rank in (0..8).rev() {
+            let mut row = String::new();
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.pieces[rank * 8 + file] {
+                    Some(piece) => {
+                        if empty > 0 {
+                            row.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        row.push(PIECE_CHARS[piece]);
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                row.push_str(&empty.to_string());
+            }
+            placement.push(row);
+        }
+
+        let castling = ['K', 'Q', 'k', 'q']
+            .into_iter()
+            .zip(self.castling)
+            .filter(|(_, allowed)| *allowed)
+            .map(|(right, _)| right)
+            .collect::<String>();
+        format!(
+            "{} {} {} {} 0 1",
+            placement.join("/"),
+            if self.white_to_move { "w" } else { "b" },
+            if castling.is_empty() { "-".to_string() } else { castling },
+            self.en_passant.map_or("-".to_string(), square_name)
+        )
+    } //
+
+    /// Squares a pawn could have skipped with a double step on the last move
+    pub fn en_passant_candidates(&self) -> Vec<usize> {
+        // the pawn that moved belongs to the side not to move
+        let (rank, pawn, step): (usize, usize, isize) = if self.white_to_move {
+            (5, 6, -8)
+        } else {
+            (2, 0, 8)
+        };
+        (0..8)
+            .map(|file| rank * 8 + file)
+            .filter(|&square| {
+                let pawn_square = (square as isize + step) as usize;
+                let start_square = (square as isize - step) as usize;
+                self.pieces[square].is_none()
+                    && self.pieces[start_square].is_none()
+                    && self.pieces[pawn_square] == Some(pawn)
+            })
+            .collect()
+    } //
+
+    pub fn validate(&self) -> Result<(), SetupError> {
+        let count = |piece: usize| self.pieces.iter().filter(|&&p| p == Some(piece)).count();
+        let (white, black) = (count(5), count(11));
+        if white != 1 || black != 1 {
+            return Err(SetupError::KingCount { white, black });
+        }
+        if count(0) > 8 || count(6) > 8 {
+            return Err(SetupError::TooManyPawns);
+        }
+        let back_ranks = (0..8).chain(56..64);
+        if back_ranks
+            .into_iter()
+            .any(|square| matches!(self.pieces[square], Some(0) | Some(6)))
+        {
+            return Err(SetupError::PawnOnBackRank);
+        }
+
+        // king and rook on their start squares for every right claimed
+        let castling_squares = [(4, 5, 7, 3), (4, 5, 0, 3), (60, 11, 63, 9), (60, 11, 56, 9)];
+        for (index, (king_square, king, rook_square, rook)) in castling_squares.into_iter().enumerate() {
+            if self.castling[index]
+                && (self.pieces[king_square] != Some(king) || self.pieces[rook_square] != Some(rook))
+            {
+                return Err(SetupError::CastlingRights);
+            }
+        }
+        if let Some(square) = self.en_passant {
+            if !self.en_passant_candidates().contains(&square) {
+                return Err(SetupError::EnPassant);
+            }
+        }
+
+        let mut board = Board::new();
+        board.load_from_fen(&self.to_fen());
+        let waiting = match board.turn {
+            Turn::WHITE => Turn::BLACK,
+            Turn::BLACK => Turn::WHITE,
+        };
+        if board.is_king_in_check(waiting) {
+            return Err(SetupError::OpponentInCheck);
+        }
+        Ok(())
+    } //
+}
+
+fn square_name(square: usize) -> String {
+    format!("{}{}", (b'a' + (square % 8) as u8) as char, square / 8 + 1)
+} //
+
+fn square_index(name: &str) -> Option<usize> {
+    let mut chars = name.chars();
+    let file = chars.next().filter(|c| ('a'..='h').contains(c))?;
+    let rank = chars.next().filter(|c| ('1'..='8').contains(c))?;
+    Some((rank as u8 - b'1') as usize * 8 + (file as u8 - b'a') as usize)
+} //
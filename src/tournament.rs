@@ -1,5 +1,6 @@
 use crate::engine::Engine;
-use crate::game::{Game, GameResult, TimeControl};
+use crate::game::{Game, GameResult, Outcome, TimeControl};
+use crate::pgn::game_result_to_pgn;
 
 #[derive(Debug)]
 pub struct TournamentResult {
@@ -9,6 +10,8 @@ pub struct TournamentResult {
     engine1_won: u64,
     engine2_won: u64,
     draws: u64,
+    /// aborted games, or games stopped before a result, counted in `total_games` only
+    unfinished: u64,
     total_games: u64,
 }
 impl TournamentResult {
@@ -20,6 +23,7 @@ impl TournamentResult {
             engine1_won: 0,
             engine2_won: 0,
             draws: 0,
+            unfinished: 0,
             total_games: 0,
         }
     }
@@ -39,9 +43,49 @@ impl TournamentResult {
             engine1_won,
             engine2_won,
             draws,
+            unfinished: 0,
             total_games,
         }
     }
+    /// Games in `total_games` that ended without a result
+    pub fn with_unfinished(mut self, unfinished: u64) -> Self {
+        self.unfinished = unfinished;
+        self
+    }
+
+    pub fn engine1(&self) -> &str {
+        &self.engine1
+    }
+    pub fn engine2(&self) -> &str {
+        &self.engine2
+    }
+    pub fn games_list(&self) -> &Vec<GameResult> {
+        &self.games_list
+    }
+    pub fn engine1_won(&self) -> u64 {
+        self.engine1_won
+    }
+    pub fn engine2_won(&self) -> u64 {
+        self.engine2_won
+    }
+    pub fn draws(&self) -> u64 {
+        self.draws
+    }
+    pub fn unfinished(&self) -> u64 {
+        self.unfinished
+    }
+    pub fn total_games(&self) -> u64 {
+        self.total_games
+    }
+
+    /// All games of the match as one PGN file, rounds numbered from 1
+    pub fn to_pgn(&self, with_comments: bool) -> String {
+        self.games_list
+            .iter()
+            .enumerate()
+            .map(|(round, game)| game_result_to_pgn(game, Some(round as u32 + 1), with_comments))
+            .collect()
+    }
 }
 
 pub struct Tournament {
@@ -61,31 +105,36 @@ impl Tournament {
         }
     } //
 
-    // pub fn start(&mut self) -> TournamentResult {
-    //     let mut tournament_result = TournamentResult::default();
-    //     tournament_result.engine1 = self.engine1.name.clone();
-    //     tournament_result.engine2 = self.engine2.name.clone();
-    //     for i in 0..self.rounds {
-    //         let engine1 = Engine::new(&self.engine1.path, &self.engine1.name);
-    //         let engine2 = Engine::new(&self.engine2.path, &self.engine2.name);
-    //         let mut game;
-    //         if i % 2 == 0 {
-    //             game = Game::new(engine1, engine2, self.time_control);
-    //         } else {
-    //             game = Game::new(engine2, engine1, self.time_control);
-    //         }
-    //         let game_result = game.play();
-    //         tournament_result.games_list.push(game_result.clone());
-    //         tournament_result.total_games += 1;
+    pub fn start(&mut self) -> TournamentResult {
+        let mut tournament_result = TournamentResult::default();
+        tournament_result.engine1 = self.engine1.name.clone();
+        tournament_result.engine2 = self.engine2.name.clone();
+        for i in 0..self.rounds {
+            // cloned engines carry no process handle, so every game spawns fresh ones with the
+            // configured option values
+            let engine1 = self.engine1.clone();
+            let engine2 = self.engine2.clone();
+            let engine1_is_white = i % 2 == 0;
+            let mut game = if engine1_is_white {
+                Game::new(engine1, engine2, self.time_control)
+            } else {
+                Game::new(engine2, engine1, self.time_control)
+            };
+            let game_result = game.play();
+            tournament_result.total_games += 1;
 
-    //         if game_result.winner() == self.engine1.name {
-    //             tournament_result.engine1_won += 1;
-    //         } else if game_result.winner() == self.engine2.name {
-    //             tournament_result.engine2_won += 1;
-    //         } else {
-    //             tournament_result.draws += 1;
-    //         }
-    //     }
-    //     tournament_result
-    // } //
+            match (game_result.outcome(), engine1_is_white) {
+                (Outcome::WhiteWin, true) | (Outcome::BlackWin, false) => {
+                    tournament_result.engine1_won += 1
+                }
+                (Outcome::WhiteWin, false) | (Outcome::BlackWin, true) => {
+                    tournament_result.engine2_won += 1
+                }
+                (Outcome::Draw, _) => tournament_result.draws += 1,
+                (Outcome::Unfinished, _) => tournament_result.unfinished += 1,
+            }
+            tournament_result.games_list.push(game_result);
+        }
+        tournament_result
+    } //
 }
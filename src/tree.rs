@@ -0,0 +1,150 @@
+use queenfish::board::Move;
+
+/// The start position, the only node without a move
+pub const ROOT: usize = 0;
+
+#[derive(Clone)]
+struct TreeNode {
+    mv: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// PGN annotations, the root's comment is the one before the first move
+    comment: Option<String>,
+    nags: Vec<u8>,
+}
+
+/// Moves played from a start position. The first child of a node continues its line,
+/// the others are side variations. Deleted nodes stay in the arena but are unreachable.
+#[derive(Clone)]
+pub struct GameTree {
+    nodes: Vec<TreeNode>,
+}
+
+impl Default for GameTree {
+    fn default() -> Self {
+        GameTree::new()
+    }
+}
+
+impl GameTree {
+    pub fn new() -> Self {
+        GameTree {
+            nodes: vec![TreeNode {
+                mv: None,
+                parent: None,
+                children: Vec::new(),
+                comment: None,
+                nags: Vec::new(),
+            }],
+        }
+    } //
+
+    /// Adds `mv` after `parent` and returns its node. A move that is already there is
+    /// reused, a new one becomes the main line only if `parent` had no moves after it.
+    pub fn add_move(&mut self, parent: usize, mv: Move) -> usize {
+        let existing = self.nodes[parent]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.nodes[child].mv.is_some_and(|other| other.to_uci() == mv.to_uci()));
+        if let Some(child) = existing {
+            return child;
+        }
+        let node = self.nodes.len();
+        self.nodes.push(TreeNode {
+            mv: Some(mv),
+            parent: Some(parent),
+            children: Vec::new(),
+            comment: None,
+            nags: Vec::new(),
+        });
+        self.nodes[parent].children.push(node);
+        node
+    } //
+
+    pub fn mv(&self, node: usize) -> Option<Move> {
+        self.nodes[node].mv
+    } //
+
+    pub fn parent(&self, node: usize) -> Option<usize> {
+        self.nodes[node].parent
+    } //
+
+    pub fn children(&self, node: usize) -> &[usize] {
+        &self.nodes[node].children
+    } //
+
+    pub fn comment(&self, node: usize) -> Option<&str> {
+        self.nodes[node].comment.as_deref()
+    } //
+
+    pub fn nags(&self, node: usize) -> &[u8] {
+        &self.nodes[node].nags
+    } //
+
+    /// Replaces the comment and NAGs of `node`
+    pub fn annotate(&mut self, node: usize, comment: Option<String>, nags: Vec<u8>) {
+        self.nodes[node].comment = comment;
+        self.nodes[node].nags = nags;
+    } //
+
+    /// Nodes from the first move to `node`, the root excluded
+    pub fn path(&self, node: usize) -> Vec<usize> {
+        let mut path = Vec::new();
+        let mut current = node;
+        while let Some(parent) = self.nodes[current].parent {
+            path.push(current);
+            current = parent;
+        }
+        path.reverse();
+        path
+    } //
+
+    /// The path to `node` followed by the main continuation after it
+    pub fn line(&self, node: usize) -> Vec<usize> {
+        let mut line = self.path(node);
+        let mut current = node;
+        while let Some(&child) = self.nodes[current].children.first() {
+            line.push(child);
+            current = child;
+        }
+        line
+    } //
+
+    pub fn line_moves(&self, node: usize) -> Vec<Move> {
+        self.line(node)
+            .into_iter()
+            .filter_map(|node| self.nodes[node].mv)
+            .collect()
+    } //
+
+    pub fn mainline(&self) -> Vec<usize> {
+        self.line(ROOT)
+    } //
+
+    pub fn is_mainline(&self, node: usize) -> bool {
+        self.path(node)
+            .into_iter()
+            .all(|node| self.nodes[node].parent.is_some_and(|parent| self.nodes[parent].children[0] == node))
+    } //
+
+    /// Makes the line through `node` the main line at every branch above it
+    pub fn promote(&mut self, node: usize) {
+        let mut current = node;
+        while let Some(parent) = self.nodes[current].parent {
+            let children = &mut self.nodes[parent].children;
+            if let Some(index) = children.iter().position(|&child| child == current) {
+                let child = children.remove(index);
+                children.insert(0, child);
+            }
+            current = parent;
+        }
+    } //
+
+    /// Removes `node` and everything after it, returning its parent. The root cannot be deleted.
+    pub fn delete(&mut self, node: usize) -> Option<usize> {
+        let parent = self.nodes[node].parent?;
+        self.nodes[parent].children.retain(|&child| child != node);
+        Some(parent)
+    } //
+}